@@ -0,0 +1,3 @@
+ALTER TABLE depth_price_history ADD COLUMN pool TEXT NOT NULL DEFAULT 'BTC.BTC';
+ALTER TABLE depth_price_history ALTER COLUMN pool DROP DEFAULT;
+CREATE INDEX depth_price_history_pool_start_time_idx ON depth_price_history (pool, start_time);
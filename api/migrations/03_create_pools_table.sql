@@ -0,0 +1,6 @@
+CREATE TABLE pools (
+  asset TEXT PRIMARY KEY,
+  status TEXT NOT NULL,
+  first_seen TIMESTAMPTZ NOT NULL,
+  last_seen TIMESTAMPTZ NOT NULL
+);
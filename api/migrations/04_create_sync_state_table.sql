@@ -0,0 +1,17 @@
+CREATE TABLE sync_state (
+  resource TEXT NOT NULL,
+  pool TEXT NOT NULL DEFAULT '',
+  last_end_time TIMESTAMPTZ NOT NULL,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (resource, pool)
+);
+
+-- Seed cursors from data already ingested
+INSERT INTO sync_state (resource, pool, last_end_time)
+SELECT 'depth_price_history', pool, max(end_time) FROM depth_price_history GROUP BY pool;
+INSERT INTO sync_state (resource, last_end_time)
+SELECT 'earnings_history', max(end_time) FROM earnings_history HAVING max(end_time) IS NOT NULL;
+INSERT INTO sync_state (resource, last_end_time)
+SELECT 'rune_pool_history', max(end_time) FROM rune_pool_history HAVING max(end_time) IS NOT NULL;
+INSERT INTO sync_state (resource, last_end_time)
+SELECT 'swaps_history', max(end_time) FROM swaps_history HAVING max(end_time) IS NOT NULL;
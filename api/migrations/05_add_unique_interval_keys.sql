@@ -0,0 +1,28 @@
+-- Drop duplicate intervals, keeping the most recently inserted row
+DELETE FROM depth_price_history a
+USING depth_price_history b
+WHERE a.pool = b.pool AND a.start_time = b.start_time AND a.id < b.id;
+
+DELETE FROM earnings_history a
+USING earnings_history b
+WHERE a.start_time = b.start_time AND a.id < b.id;
+
+DELETE FROM pool_earnings a
+USING pool_earnings b
+WHERE a.earnings_history_id = b.earnings_history_id AND a.pool = b.pool AND a.id < b.id;
+
+DELETE FROM rune_pool_history a
+USING rune_pool_history b
+WHERE a.start_time = b.start_time AND a.id < b.id;
+
+DELETE FROM swaps_history a
+USING swaps_history b
+WHERE a.start_time = b.start_time AND a.id < b.id;
+
+DROP INDEX depth_price_history_pool_start_time_idx;
+
+ALTER TABLE depth_price_history ADD CONSTRAINT depth_price_history_pool_start_time_key UNIQUE (pool, start_time);
+ALTER TABLE earnings_history ADD CONSTRAINT earnings_history_start_time_key UNIQUE (start_time);
+ALTER TABLE pool_earnings ADD CONSTRAINT pool_earnings_earnings_history_id_pool_key UNIQUE (earnings_history_id, pool);
+ALTER TABLE rune_pool_history ADD CONSTRAINT rune_pool_history_start_time_key UNIQUE (start_time);
+ALTER TABLE swaps_history ADD CONSTRAINT swaps_history_start_time_key UNIQUE (start_time);
@@ -0,0 +1,13 @@
+ALTER TABLE depth_price_history ADD COLUMN is_final BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE earnings_history ADD COLUMN is_final BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE rune_pool_history ADD COLUMN is_final BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE swaps_history ADD COLUMN is_final BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- Intervals that had not closed when they were stored
+UPDATE depth_price_history SET is_final = FALSE WHERE end_time > now();
+UPDATE earnings_history SET is_final = FALSE WHERE end_time > now();
+UPDATE rune_pool_history SET is_final = FALSE WHERE end_time > now();
+UPDATE swaps_history SET is_final = FALSE WHERE end_time > now();
+
+-- Rewind cursors that moved past an open interval so it gets re-fetched
+UPDATE sync_state SET last_end_time = date_trunc('hour', now()) WHERE last_end_time > now();
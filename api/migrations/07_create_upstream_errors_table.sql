@@ -0,0 +1,13 @@
+CREATE TABLE upstream_errors (
+  id SERIAL PRIMARY KEY,
+  run_started_at TIMESTAMPTZ NOT NULL,
+  resource TEXT NOT NULL,
+  pool TEXT NOT NULL DEFAULT '',
+  kind TEXT NOT NULL,
+  url TEXT,
+  http_status INTEGER,
+  detail TEXT NOT NULL,
+  occurred_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX upstream_errors_run_started_at_idx ON upstream_errors (run_started_at);
@@ -0,0 +1,15 @@
+CREATE TABLE sync_runs (
+  id SERIAL PRIMARY KEY,
+  started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  finished_at TIMESTAMPTZ,
+  status TEXT NOT NULL DEFAULT 'running',
+  resources TEXT[] NOT NULL DEFAULT '{}',
+  windows_fetched INTEGER NOT NULL DEFAULT 0,
+  rows_upserted BIGINT NOT NULL DEFAULT 0,
+  retries BIGINT NOT NULL DEFAULT 0,
+  error TEXT
+);
+
+CREATE INDEX sync_runs_started_at_idx ON sync_runs (started_at);
+
+ALTER TABLE upstream_errors ADD COLUMN run_id INTEGER REFERENCES sync_runs (id);
@@ -0,0 +1,2 @@
+-- Scheduled ticks, on-demand syncs and range backfills share the run log
+ALTER TABLE sync_runs ADD COLUMN trigger TEXT NOT NULL DEFAULT 'scheduler';
@@ -0,0 +1,9 @@
+-- Planned windows of a first-time historical backfill, so it resumes after a restart
+CREATE TABLE backfill_windows (
+  resource TEXT NOT NULL,
+  pool TEXT NOT NULL DEFAULT '',
+  start_time TIMESTAMPTZ NOT NULL,
+  end_time TIMESTAMPTZ NOT NULL,
+  completed_at TIMESTAMPTZ,
+  PRIMARY KEY (resource, pool, start_time)
+);
@@ -0,0 +1,7 @@
+-- Midgard resolution each row was fetched at, older history may be stored per day
+ALTER TABLE depth_price_history ADD COLUMN interval TEXT NOT NULL DEFAULT 'hour';
+ALTER TABLE earnings_history ADD COLUMN interval TEXT NOT NULL DEFAULT 'hour';
+ALTER TABLE rune_pool_history ADD COLUMN interval TEXT NOT NULL DEFAULT 'hour';
+ALTER TABLE swaps_history ADD COLUMN interval TEXT NOT NULL DEFAULT 'hour';
+
+ALTER TABLE backfill_windows ADD COLUMN interval TEXT NOT NULL DEFAULT 'hour';
@@ -0,0 +1,14 @@
+CREATE TABLE raw_responses (
+  id BIGSERIAL PRIMARY KEY,
+  resource TEXT NOT NULL,
+  pool TEXT NOT NULL DEFAULT '',
+  url TEXT NOT NULL,
+  interval TEXT NOT NULL,
+  from_time TIMESTAMPTZ NOT NULL,
+  count INTEGER NOT NULL,
+  fetched_at TIMESTAMPTZ NOT NULL,
+  content_hash TEXT NOT NULL,
+  body BYTEA NOT NULL
+);
+
+CREATE INDEX raw_responses_resource_idx ON raw_responses (resource, pool, from_time);
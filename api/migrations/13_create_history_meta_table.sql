@@ -0,0 +1,10 @@
+CREATE TABLE history_meta (
+  resource TEXT NOT NULL,
+  pool TEXT NOT NULL DEFAULT '',
+  interval TEXT NOT NULL,
+  start_time TIMESTAMPTZ NOT NULL,
+  end_time TIMESTAMPTZ NOT NULL,
+  meta JSONB NOT NULL,
+  fetched_at TIMESTAMPTZ NOT NULL,
+  PRIMARY KEY (resource, pool, interval, start_time)
+);
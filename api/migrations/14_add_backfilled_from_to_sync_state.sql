@@ -0,0 +1,2 @@
+-- Oldest time a backfill has requested, so history Midgard never had is not re-fetched
+ALTER TABLE sync_state ADD COLUMN backfilled_from TIMESTAMPTZ;
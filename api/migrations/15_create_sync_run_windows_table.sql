@@ -0,0 +1,14 @@
+-- Every Midgard window a sync run wrote, with the range it requested
+CREATE TABLE sync_run_windows (
+  id BIGSERIAL PRIMARY KEY,
+  run_id INTEGER NOT NULL REFERENCES sync_runs (id) ON DELETE CASCADE,
+  resource TEXT NOT NULL,
+  pool TEXT NOT NULL DEFAULT '',
+  interval TEXT NOT NULL,
+  from_time TIMESTAMPTZ NOT NULL,
+  count INTEGER NOT NULL,
+  rows_upserted INTEGER NOT NULL,
+  fetched_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX sync_run_windows_run_id_idx ON sync_run_windows (run_id, from_time);
@@ -0,0 +1,21 @@
+-- Coarse rows overlapping hourly ones counted the same range twice. They are
+-- dropped and the holes this leaves are refilled hourly by the gap backfill
+DELETE FROM depth_price_history c
+USING depth_price_history h
+WHERE c.interval <> 'hour' AND h.interval = 'hour' AND c.pool = h.pool
+  AND c.start_time < h.end_time AND c.end_time > h.start_time;
+
+DELETE FROM earnings_history c
+USING earnings_history h
+WHERE c.interval <> 'hour' AND h.interval = 'hour'
+  AND c.start_time < h.end_time AND c.end_time > h.start_time;
+
+DELETE FROM rune_pool_history c
+USING rune_pool_history h
+WHERE c.interval <> 'hour' AND h.interval = 'hour'
+  AND c.start_time < h.end_time AND c.end_time > h.start_time;
+
+DELETE FROM swaps_history c
+USING swaps_history h
+WHERE c.interval <> 'hour' AND h.interval = 'hour'
+  AND c.start_time < h.end_time AND c.end_time > h.start_time;
@@ -1,32 +1,85 @@
+pub mod auth;
 pub mod routes;
 
+use crate::db::history_meta::get_history_meta;
+use crate::models::{HistoryMeta, Resource};
+use crate::state::AppState;
 use crate::utils::parse_date_to_utc;
 use axum::{
     extract::{Query, State},
     response::Json,
+    routing::{get, post},
+    Router,
 };
+use chrono::{DateTime, Utc};
 use reqwest::StatusCode;
+use routes::{
+    docs, get_depth_price_history, get_earnings_history, get_gaps, get_pool, get_pools,
+    get_rune_pool_history, get_swaps_history, get_sync_run_windows, get_sync_runs, get_sync_status,
+    trigger_backfill, trigger_sync,
+};
+use serde::Serialize;
 use sqlx::{FromRow, PgPool, Postgres, QueryBuilder};
 use std::sync::Arc;
+use tower_http::trace::TraceLayer;
+
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(docs))
+        .route("/history/depth", get(get_depth_price_history))
+        .route("/history/earnings", get(get_earnings_history))
+        .route("/history/rune-pool", get(get_rune_pool_history))
+        .route("/history/swaps", get(get_swaps_history))
+        .route("/pools", get(get_pools))
+        .route("/pools/:asset", get(get_pool))
+        .route("/admin/gaps", get(get_gaps))
+        .route("/admin/sync/runs", get(get_sync_runs))
+        .route("/admin/sync/runs/:id/windows", get(get_sync_run_windows))
+        .route("/admin/sync/status", get(get_sync_status))
+        .route("/admin/sync", post(trigger_sync))
+        .route("/admin/backfill", post(trigger_backfill))
+        .with_state(state)
+        .layer(tower_http::catch_panic::CatchPanicLayer::new())
+        .layer(TraceLayer::new_for_http())
+}
 
 #[derive(serde::Deserialize)]
 pub struct ApiParams {
+    pub pool: Option<String>,
     interval: Option<String>,
     date_range: Option<String>,
     sort_by: Option<String>,
     order: Option<String>,
     limit: Option<i64>,
     page: Option<i64>,
+    meta: Option<bool>,
+}
+
+// Plain intervals, or with `meta=true` also Midgard's summaries of the
+// fetch windows they came from
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum HistoryBody<T> {
+    Intervals(Vec<T>),
+    WithMeta {
+        meta: Vec<HistoryMeta>,
+        intervals: Vec<T>,
+    },
 }
 
 pub async fn get_history<T>(
     State(db_pool): State<Arc<PgPool>>,
     Query(params): Query<ApiParams>,
-    table: &str,
-) -> Result<Json<Vec<T>>, (StatusCode, String)>
+    resource: Resource,
+    pool: Option<&str>,
+    span: impl Fn(&T) -> (DateTime<Utc>, DateTime<Utc>),
+) -> Result<Json<HistoryBody<T>>, (StatusCode, String)>
 where
     T: for<'r> FromRow<'r, sqlx::postgres::PgRow> + Send + Sync + Unpin + 'static,
 {
+    let table = resource.as_str();
+    let include_meta = params.meta.unwrap_or(false);
+
     // Calculate interval
     let allowed_intervals = vec!["hour", "day", "week", "month"];
     let interval = match &params.interval {
@@ -56,30 +109,32 @@ where
     };
     let mut query: QueryBuilder<Postgres> = QueryBuilder::new(select_clause);
 
+    // Collect filters
+    let mut has_filter = false;
+    let mut push_filter = |query: &mut QueryBuilder<Postgres>| {
+        query.push(if has_filter { " AND " } else { " WHERE " });
+        has_filter = true;
+    };
+
+    if let Some(pool) = pool {
+        push_filter(&mut query);
+        query.push("pool = ").push_bind(pool.to_string());
+    }
+
     // Parse the dates if provided
     if let Some(date_range) = &params.date_range {
         let dates: Vec<&str> = date_range.split(',').collect();
 
-        let mut start_date = None;
-        let mut end_date = None;
-
         if !dates[0].is_empty() {
-            start_date = parse_date_to_utc(dates[0]);
+            if let Some(start) = parse_date_to_utc(dates[0]) {
+                push_filter(&mut query);
+                query.push("start_time >= ").push_bind(start);
+            }
         }
 
         if dates.len() > 1 && !dates[1].is_empty() {
-            end_date = parse_date_to_utc(dates[1]);
-        }
-
-        if start_date.is_some() || end_date.is_some() {
-            query.push(" WHERE ");
-            if let Some(start) = start_date {
-                query.push("start_time >= ").push_bind(start);
-            }
-            if let Some(end) = end_date {
-                if start_date.is_some() {
-                    query.push(" AND ");
-                }
+            if let Some(end) = parse_date_to_utc(dates[1]) {
+                push_filter(&mut query);
                 query.push("end_time <= ").push_bind(end);
             }
         }
@@ -117,5 +172,32 @@ where
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok(Json(records))
+    history_body(&db_pool, include_meta, resource, pool, records, span).await
+}
+
+pub async fn history_body<T>(
+    db_pool: &PgPool,
+    include_meta: bool,
+    resource: Resource,
+    pool: Option<&str>,
+    intervals: Vec<T>,
+    span: impl Fn(&T) -> (DateTime<Utc>, DateTime<Utc>),
+) -> Result<Json<HistoryBody<T>>, (StatusCode, String)> {
+    if !include_meta {
+        return Ok(Json(HistoryBody::Intervals(intervals)));
+    }
+
+    // Sorting may reorder the page, so take the range from every interval
+    let start_time = intervals.iter().map(|item| span(item).0).min();
+    let end_time = intervals.iter().map(|item| span(item).1).max();
+    let meta = match (start_time, end_time) {
+        (Some(start_time), Some(end_time)) => {
+            get_history_meta(db_pool, resource, pool, start_time, end_time)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        }
+        _ => Vec::new(),
+    };
+
+    Ok(Json(HistoryBody::WithMeta { meta, intervals }))
 }
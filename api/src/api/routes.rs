@@ -1,11 +1,19 @@
 mod depth_price_history;
 mod docs;
 mod earnings_history;
+mod gaps;
+mod pools;
 mod rune_pool_history;
 mod swaps_history;
+mod sync;
+mod sync_runs;
 
 pub use depth_price_history::get_depth_price_history;
 pub use docs::docs;
 pub use earnings_history::get_earnings_history;
+pub use gaps::get_gaps;
+pub use pools::{get_pool, get_pools};
 pub use rune_pool_history::get_rune_pool_history;
 pub use swaps_history::get_swaps_history;
+pub use sync::{trigger_backfill, trigger_sync};
+pub use sync_runs::{get_sync_run_windows, get_sync_runs, get_sync_status};
@@ -1,6 +1,7 @@
 use crate::{
-    api::{get_history, ApiParams},
-    models::DepthPriceHistory,
+    api::{get_history, ApiParams, HistoryBody},
+    config::DEFAULT_POOL,
+    models::{DepthPriceHistory, Resource},
 };
 use axum::{
     extract::{Query, State},
@@ -13,6 +14,25 @@ use std::sync::Arc;
 pub async fn get_depth_price_history(
     state: State<Arc<PgPool>>,
     params: Query<ApiParams>,
-) -> Result<Json<Vec<DepthPriceHistory>>, (StatusCode, String)> {
-    get_history::<DepthPriceHistory>(state, params, "depth_price_history").await
+) -> Result<Json<HistoryBody<DepthPriceHistory>>, (StatusCode, String)> {
+    let pool = params
+        .pool
+        .clone()
+        .unwrap_or_else(|| DEFAULT_POOL.to_string());
+
+    // Reject pools we have never ingested
+    let known_pool: bool =
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM depth_price_history WHERE pool = $1)")
+            .bind(&pool)
+            .fetch_one(&**state)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !known_pool {
+        return Err((StatusCode::BAD_REQUEST, format!("Unknown pool: {}", pool)));
+    }
+
+    get_history::<DepthPriceHistory>(state, params, Resource::Depth, Some(&pool), |item| {
+        (item.start_time, item.end_time)
+    })
+    .await
 }
@@ -1,5 +1,8 @@
 use crate::utils::parse_date_to_utc;
-use crate::{api::ApiParams, models::ApiEarningsHistory};
+use crate::{
+    api::{history_body, ApiParams, HistoryBody},
+    models::{ApiEarningsHistory, Resource},
+};
 use axum::{
     extract::{Query, State},
     response::Json,
@@ -12,7 +15,8 @@ use std::sync::Arc;
 pub async fn get_earnings_history(
     State(db_pool): State<Arc<PgPool>>,
     Query(params): Query<ApiParams>,
-) -> Result<Json<Vec<ApiEarningsHistory>>, (StatusCode, String)> {
+) -> Result<Json<HistoryBody<ApiEarningsHistory>>, (StatusCode, String)> {
+    let include_meta = params.meta.unwrap_or(false);
     let mut query = String::new();
 
     // Calculate interval
@@ -44,6 +48,8 @@ pub async fn get_earnings_history(
             e.liquidity_earnings,
             e.avg_node_count,
             e.rune_price_usd,
+            e.is_final,
+            e.interval,
             json_agg(json_build_object(
                 'pool', pe.pool,
                 'asset_liquidity_fees', pe.asset_liquidity_fees,
@@ -55,7 +61,7 @@ pub async fn get_earnings_history(
             )) AS pools
         FROM earnings_history e
         LEFT JOIN pool_earnings pe ON e.id = pe.earnings_history_id
-        GROUP BY e.start_time, e.end_time, e.liquidity_fees, e.block_rewards, e.earnings, e.bonding_earnings, e.liquidity_earnings, e.avg_node_count, e.rune_price_usd
+        GROUP BY e.start_time, e.end_time, e.liquidity_fees, e.block_rewards, e.earnings, e.bonding_earnings, e.liquidity_earnings, e.avg_node_count, e.rune_price_usd, e.is_final, e.interval
     "#;
 
     let select_clause = if interval.is_empty() {
@@ -139,6 +145,8 @@ pub async fn get_earnings_history(
             let liquidity_earnings: i64 = row.get("liquidity_earnings");
             let avg_node_count: sqlx::types::BigDecimal = row.get("avg_node_count");
             let rune_price_usd: sqlx::types::BigDecimal = row.get("rune_price_usd");
+            let is_final: bool = row.get("is_final");
+            let interval: String = row.get("interval");
 
             // Parse pools JSON
             let pools_json = row.get("pools");
@@ -155,9 +163,19 @@ pub async fn get_earnings_history(
                 avg_node_count,
                 rune_price_usd,
                 pools,
+                is_final,
+                interval,
             }
         })
         .collect::<Vec<ApiEarningsHistory>>();
 
-    Ok(Json(earnings_history))
+    history_body(
+        &db_pool,
+        include_meta,
+        Resource::Earnings,
+        None,
+        earnings_history,
+        |item| (item.start_time, item.end_time),
+    )
+    .await
 }
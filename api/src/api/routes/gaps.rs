@@ -0,0 +1,24 @@
+use crate::{
+    api::auth::AdminAuth,
+    db::gaps::find_gaps,
+    models::{Gap, Resource},
+};
+use axum::{extract::State, response::Json};
+use reqwest::StatusCode;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+pub async fn get_gaps(
+    _auth: AdminAuth,
+    State(db_pool): State<Arc<PgPool>>,
+) -> Result<Json<Vec<Gap>>, (StatusCode, String)> {
+    let mut gaps = Vec::new();
+    for resource in Resource::ALL {
+        let resource_gaps = find_gaps(&db_pool, resource)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        gaps.extend(resource_gaps);
+    }
+
+    Ok(Json(gaps))
+}
@@ -0,0 +1,46 @@
+use crate::models::ApiPool;
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use reqwest::StatusCode;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+#[derive(serde::Deserialize)]
+pub struct PoolsParams {
+    status: Option<String>,
+}
+
+pub async fn get_pools(
+    State(db_pool): State<Arc<PgPool>>,
+    Query(params): Query<PoolsParams>,
+) -> Result<Json<Vec<ApiPool>>, (StatusCode, String)> {
+    let pools = sqlx::query_as::<_, ApiPool>(
+        "SELECT asset, status, first_seen, last_seen FROM pools
+         WHERE $1::TEXT IS NULL OR status = $1
+         ORDER BY asset",
+    )
+    .bind(params.status)
+    .fetch_all(&*db_pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(pools))
+}
+
+pub async fn get_pool(
+    State(db_pool): State<Arc<PgPool>>,
+    Path(asset): Path<String>,
+) -> Result<Json<ApiPool>, (StatusCode, String)> {
+    let pool = sqlx::query_as::<_, ApiPool>(
+        "SELECT asset, status, first_seen, last_seen FROM pools WHERE asset = $1",
+    )
+    .bind(&asset)
+    .fetch_optional(&*db_pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .ok_or((StatusCode::NOT_FOUND, format!("Unknown pool: {}", asset)))?;
+
+    Ok(Json(pool))
+}
@@ -1,6 +1,6 @@
 use crate::{
-    api::{get_history, ApiParams},
-    models::RunePoolHistory,
+    api::{get_history, ApiParams, HistoryBody},
+    models::{Resource, RunePoolHistory},
 };
 use axum::{
     extract::{Query, State},
@@ -13,6 +13,9 @@ use std::sync::Arc;
 pub async fn get_rune_pool_history(
     state: State<Arc<PgPool>>,
     params: Query<ApiParams>,
-) -> Result<Json<Vec<RunePoolHistory>>, (StatusCode, String)> {
-    get_history::<RunePoolHistory>(state, params, "rune_pool_history").await
+) -> Result<Json<HistoryBody<RunePoolHistory>>, (StatusCode, String)> {
+    get_history::<RunePoolHistory>(state, params, Resource::RunePool, None, |item| {
+        (item.start_time, item.end_time)
+    })
+    .await
 }
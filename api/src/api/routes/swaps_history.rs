@@ -1,6 +1,6 @@
 use crate::{
-    api::{get_history, ApiParams},
-    models::SwapsHistory,
+    api::{get_history, ApiParams, HistoryBody},
+    models::{Resource, SwapsHistory},
 };
 use axum::{
     extract::{Query, State},
@@ -13,6 +13,9 @@ use std::sync::Arc;
 pub async fn get_swaps_history(
     state: State<Arc<PgPool>>,
     params: Query<ApiParams>,
-) -> Result<Json<Vec<SwapsHistory>>, (StatusCode, String)> {
-    get_history::<SwapsHistory>(state, params, "swaps_history").await
+) -> Result<Json<HistoryBody<SwapsHistory>>, (StatusCode, String)> {
+    get_history::<SwapsHistory>(state, params, Resource::Swaps, None, |item| {
+        (item.start_time, item.end_time)
+    })
+    .await
 }
@@ -0,0 +1,97 @@
+use crate::{
+    api::auth::AdminAuth,
+    config::DEFAULT_POOL,
+    db::insertions::is_catalog_pool,
+    models::{Resource, SyncTrigger},
+    populate_db::{backfill_bounds, backfill_range, populate_db, populate_pools},
+    state::AppState,
+};
+use axum::{extract::State, Json};
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+
+#[derive(serde::Deserialize)]
+pub struct BackfillRequest {
+    resource: String,
+    pool: Option<String>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+}
+
+pub async fn trigger_sync(
+    _auth: AdminAuth,
+    State(state): State<AppState>,
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+    require_leader(&state)?;
+
+    // Refused rather than queued, a sync right behind a running one finds little new
+    let guards = state.sync_locks.try_lock_all().ok_or((
+        StatusCode::CONFLICT,
+        "A sync is already running".to_string(),
+    ))?;
+
+    state.tasks.clone().spawn(async move {
+        let _guards = guards;
+        populate_pools(&state).await;
+        populate_db(&state, &Resource::ALL, SyncTrigger::Manual).await;
+    });
+
+    Ok((StatusCode::ACCEPTED, "Sync started".to_string()))
+}
+
+pub async fn trigger_backfill(
+    _auth: AdminAuth,
+    State(state): State<AppState>,
+    Json(request): Json<BackfillRequest>,
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+    require_leader(&state)?;
+
+    let resource: Resource = request
+        .resource
+        .parse()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let pool = match (resource.is_per_pool(), request.pool) {
+        (true, pool) => Some(pool.unwrap_or_else(|| DEFAULT_POOL.to_string())),
+        (false, None) => None,
+        (false, Some(_)) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("{resource} is not tracked per pool"),
+            ))
+        }
+    };
+
+    if let Some(pool) = &pool {
+        let known_pool = is_catalog_pool(&state.db_pool, pool)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if !known_pool {
+            return Err((StatusCode::BAD_REQUEST, format!("Unknown pool: {}", pool)));
+        }
+    }
+
+    let (from, to) = backfill_bounds(&state.config, request.from, request.to)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    // Queued behind any running sync rather than upserting the same rows concurrently
+    let message = format!("Backfill of {resource} from {from} to {to} queued");
+    state.tasks.clone().spawn(async move {
+        let _guard = state.sync_locks.lock(resource).await;
+        backfill_range(&state, resource, pool.as_deref(), from, to).await;
+    });
+
+    Ok((StatusCode::ACCEPTED, message))
+}
+
+// Writes from another replica would race the leader's scheduled runs
+fn require_leader(state: &AppState) -> Result<(), (StatusCode, String)> {
+    if state.leadership.is_leader() {
+        Ok(())
+    } else {
+        Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "This replica is not the ingestion leader".to_string(),
+        ))
+    }
+}
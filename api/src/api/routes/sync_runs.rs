@@ -0,0 +1,66 @@
+use crate::{
+    api::auth::AdminAuth,
+    db::sync_runs::{latest_sync_run, list_sync_run_windows, list_sync_runs, resource_freshness},
+    models::{SyncRun, SyncRunWindow, SyncStatus},
+    state::AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use reqwest::StatusCode;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+#[derive(serde::Deserialize)]
+pub struct SyncRunsParams {
+    limit: Option<i64>,
+}
+
+pub async fn get_sync_runs(
+    _auth: AdminAuth,
+    State(db_pool): State<Arc<PgPool>>,
+    Query(params): Query<SyncRunsParams>,
+) -> Result<Json<Vec<SyncRun>>, (StatusCode, String)> {
+    let limit = params.limit.unwrap_or(20).clamp(1, 400);
+    let runs = list_sync_runs(&db_pool, limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(runs))
+}
+
+pub async fn get_sync_run_windows(
+    _auth: AdminAuth,
+    State(db_pool): State<Arc<PgPool>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<SyncRunWindow>>, (StatusCode, String)> {
+    let windows = list_sync_run_windows(&db_pool, id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(windows))
+}
+
+pub async fn get_sync_status(
+    _auth: AdminAuth,
+    State(state): State<AppState>,
+) -> Result<Json<SyncStatus>, (StatusCode, String)> {
+    let db_pool = &state.db_pool;
+    let internal_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+
+    let last_run = latest_sync_run(db_pool, None)
+        .await
+        .map_err(internal_error)?;
+    let last_successful_run = latest_sync_run(db_pool, Some("succeeded"))
+        .await
+        .map_err(internal_error)?;
+    let resources = resource_freshness(db_pool).await.map_err(internal_error)?;
+
+    Ok(Json(SyncStatus {
+        is_leader: state.leadership.is_leader(),
+        last_run,
+        last_successful_run,
+        resources,
+    }))
+}
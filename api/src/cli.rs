@@ -0,0 +1,90 @@
+use crate::config::parse_time;
+use crate::models::{Interval, Resource};
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(version, about = "Midgard history ingestion and API server")]
+pub struct Cli {
+    // Without a subcommand the binary serves the API and runs the scheduler
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Serve the API, and run the scheduler unless --no-scheduler is given")]
+    Serve {
+        #[arg(long, help = "Only serve the API, leaving ingestion to a worker")]
+        no_scheduler: bool,
+    },
+
+    #[command(about = "Run the scheduler without serving the API")]
+    Worker,
+
+    #[command(about = "Sync from Midgard, once with --once or on the configured schedules")]
+    Sync {
+        #[arg(long, help = "Run a single sync and exit")]
+        once: bool,
+        #[arg(
+            long = "resource",
+            requires = "once",
+            help = "Only sync this resource, repeatable"
+        )]
+        resources: Vec<Resource>,
+    },
+
+    #[command(about = "Re-fetch a range from Midgard without moving the sync cursors")]
+    Backfill {
+        #[arg(long, help = "Resource to backfill, e.g. depth or swaps")]
+        resource: Resource,
+        #[arg(long, help = "Pool of a depth backfill, defaults to BTC.BTC")]
+        pool: Option<String>,
+        #[arg(long, value_parser = parse_time_arg, help = "Start of the range, a date or RFC 3339 time")]
+        from: DateTime<Utc>,
+        #[arg(long, value_parser = parse_time_arg, help = "End of the range (exclusive)")]
+        to: DateTime<Utc>,
+    },
+
+    #[command(about = "Apply pending database migrations")]
+    Migrate {
+        #[arg(
+            long,
+            help = "List applied and pending migrations without applying any"
+        )]
+        status: bool,
+    },
+
+    #[command(about = "Rebuild the history tables from archived Midgard responses, offline")]
+    Reindex {
+        #[arg(long, help = "Only replay this resource, e.g. depth or swaps")]
+        resource: Option<Resource>,
+        #[arg(long, help = "Only replay this depth pool, e.g. BTC.BTC")]
+        pool: Option<String>,
+        #[arg(long, value_parser = parse_time_arg, help = "Start of the range, a date or RFC 3339 time")]
+        from: Option<DateTime<Utc>>,
+        #[arg(long, value_parser = parse_time_arg, help = "End of the range (exclusive)")]
+        to: Option<DateTime<Utc>>,
+    },
+
+    #[command(about = "Upsert Midgard history exports shaped like a {meta, intervals} response")]
+    Import {
+        #[arg(required = true, help = "JSON files to import")]
+        files: Vec<PathBuf>,
+        #[arg(long, help = "Resource the files hold, e.g. depth or swaps")]
+        resource: Resource,
+        #[arg(long, help = "Pool of a depth export, defaults to BTC.BTC")]
+        pool: Option<String>,
+        #[arg(
+            long,
+            default_value = "hour",
+            help = "Interval the export was taken at"
+        )]
+        interval: Interval,
+    },
+}
+
+fn parse_time_arg(value: &str) -> Result<DateTime<Utc>, String> {
+    parse_time(value).ok_or_else(|| format!("Invalid time: {}", value))
+}
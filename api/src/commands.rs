@@ -0,0 +1,307 @@
+use crate::api::router;
+use crate::config::{Config, DEFAULT_POOL};
+use crate::db::insertions::is_catalog_pool;
+use crate::db::sync_runs::interrupt_running_sync_runs;
+use crate::db::{self, connect_db, init_db};
+use crate::import;
+use crate::leader::{self, Leadership};
+use crate::midgard_api::{self, MidgardClient};
+use crate::models::{Interval, Resource, SyncTrigger};
+use crate::populate_db::{backfill_bounds, backfill_range, populate_db, populate_pools};
+use crate::reindex::{self, ReindexScope};
+use crate::scheduler;
+use crate::state::{AppState, SyncLocks};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use std::{env, future::IntoFuture, path::PathBuf, sync::Arc};
+use tokio::{
+    net::TcpListener,
+    signal,
+    time::{self, Duration, Instant},
+};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+// Config, database and upstream client shared by every ingesting command
+async fn build_state() -> Result<AppState> {
+    let config = Config::from_env().context("Failed to load configuration")?;
+    let db_pool = init_db().await.context("Failed to initialize database")?;
+    println!("\nConnected to database!\n");
+    let midgard_client = MidgardClient::new(&config).context("Failed to build Midgard client")?;
+
+    Ok(AppState {
+        db_pool: Arc::new(db_pool),
+        config: Arc::new(config),
+        midgard_client: Arc::new(midgard_client),
+        sync_locks: Arc::new(SyncLocks::new()),
+        leadership: Arc::new(Leadership::new()),
+        shutdown: CancellationToken::new(),
+        tasks: TaskTracker::new(),
+    })
+}
+
+// Past the deadline, closing the unfinished runs gets this much longer
+const RUN_SWEEP_TIMEOUT: Duration = Duration::from_secs(5);
+
+// SHUTDOWN_TIMEOUT_SECS from when shutdown begins, shared by every drain step
+fn shutdown_deadline(state: &AppState) -> Instant {
+    Instant::now() + Duration::from_secs(state.config.shutdown_timeout_secs)
+}
+
+// Let in-flight ingestion commit its current window, then close the pool.
+// Anything still running at the deadline is dropped and its transaction rolled back
+async fn drain(state: &AppState, deadline: Instant) {
+    state.tasks.close();
+    if time::timeout_at(deadline, state.tasks.wait())
+        .await
+        .is_err()
+    {
+        eprintln!("Ingestion did not stop before the shutdown deadline");
+        // Dropped runs never record themselves. The lock is still held, so
+        // every running run is this process's
+        if state.leadership.is_leader() {
+            let sweep = interrupt_running_sync_runs(&state.db_pool);
+            match time::timeout(RUN_SWEEP_TIMEOUT, sweep).await {
+                Ok(Ok(count)) => println!("Marked {count} unfinished sync runs as interrupted"),
+                Ok(Err(error)) => eprintln!("Failed to mark unfinished sync runs | {error}"),
+                Err(_) => eprintln!("Timed out marking unfinished sync runs"),
+            }
+        }
+    }
+    if time::timeout_at(deadline, state.db_pool.close())
+        .await
+        .is_err()
+    {
+        eprintln!("Database pool did not close before the shutdown deadline");
+    }
+    println!("Shutdown complete");
+}
+
+async fn shutdown_signal(shutdown: CancellationToken) {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    println!("\nShutting down...");
+    shutdown.cancel();
+}
+
+// One-off jobs ingest only while no server or worker holds the leader lock
+async fn require_leadership(state: &AppState) -> Result<()> {
+    leader::start_leader_election(state.clone()).await;
+    if !state.leadership.is_leader() {
+        bail!("Another process holds ingestion leadership, try again once it has stopped");
+    }
+
+    // Stop at the next window boundary on SIGTERM/SIGINT, as the scheduler does
+    tokio::spawn(shutdown_signal(state.shutdown.clone()));
+    Ok(())
+}
+
+pub async fn serve(scheduler: bool) -> Result<()> {
+    let state = build_state().await?;
+
+    // Only the elected replica ingests, the others just serve the API
+    if scheduler {
+        leader::start_leader_election(state.clone()).await;
+        state.tasks.spawn(scheduler::start_scheduler(state.clone()));
+    } else {
+        println!("Scheduler disabled, admin sync endpoints will answer 503");
+    }
+
+    let port = env::var("PORT")
+        .unwrap_or_else(|_| "3000".to_string())
+        .parse::<u16>()
+        .context("PORT must be a number")?;
+
+    let listener = TcpListener::bind(format!("0.0.0.0:{}", port))
+        .await
+        .context("Could not create listener")?;
+
+    println!("Server running on port {}", port);
+
+    let server = axum::serve(listener, router(state.clone()))
+        .with_graceful_shutdown(shutdown_signal(state.shutdown.clone()))
+        .into_future();
+    tokio::pin!(server);
+    let finished = tokio::select! {
+        result = &mut server => {
+            result.context("Failed to start server")?;
+            true
+        }
+        _ = state.shutdown.cancelled() => false,
+    };
+
+    // Open requests get the same deadline as ingestion, then are dropped
+    let deadline = shutdown_deadline(&state);
+    if !finished {
+        match time::timeout_at(deadline, server).await {
+            Ok(result) => result.context("Failed to start server")?,
+            Err(_) => eprintln!("HTTP requests did not finish before the shutdown deadline"),
+        }
+    }
+    drain(&state, deadline).await;
+    Ok(())
+}
+
+pub async fn worker() -> Result<()> {
+    let state = build_state().await?;
+
+    leader::start_leader_election(state.clone()).await;
+    state.tasks.spawn(scheduler::start_scheduler(state.clone()));
+    println!("Worker running");
+
+    shutdown_signal(state.shutdown.clone()).await;
+    drain(&state, shutdown_deadline(&state)).await;
+    Ok(())
+}
+
+pub async fn sync_once(resources: &[Resource]) -> Result<()> {
+    let state = build_state().await?;
+    require_leadership(&state).await?;
+
+    let resources = match resources.is_empty() {
+        true => Resource::ALL.to_vec(),
+        false => resources.to_vec(),
+    };
+    if resources.contains(&Resource::Depth) {
+        populate_pools(&state).await;
+    }
+    let succeeded = populate_db(&state, &resources, SyncTrigger::Manual).await;
+
+    state.shutdown.cancel();
+    drain(&state, shutdown_deadline(&state)).await;
+    if !succeeded {
+        bail!("Sync did not complete, see sync_runs for details");
+    }
+    Ok(())
+}
+
+pub async fn backfill(
+    resource: Resource,
+    pool: Option<String>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<()> {
+    let pool = pool_for(resource, pool)?;
+    let state = build_state().await?;
+    if let Some(pool) = &pool {
+        if !is_catalog_pool(&state.db_pool, pool).await? {
+            bail!("Unknown pool: {pool}");
+        }
+    }
+    let (from, to) = backfill_bounds(&state.config, from, to).map_err(anyhow::Error::msg)?;
+    require_leadership(&state).await?;
+
+    let succeeded = backfill_range(&state, resource, pool.as_deref(), from, to).await;
+
+    state.shutdown.cancel();
+    drain(&state, shutdown_deadline(&state)).await;
+    if !succeeded {
+        bail!("Backfill did not complete, see sync_runs for details");
+    }
+    Ok(())
+}
+
+pub async fn migrate(status: bool) -> Result<()> {
+    let db_pool = connect_db()
+        .await
+        .context("Failed to connect to database")?;
+
+    if !status {
+        db::MIGRATOR
+            .run(&db_pool)
+            .await
+            .context("Failed to apply migrations")?;
+        println!("Migrations are up to date");
+    }
+
+    for migration in db::migration_status(&db_pool).await? {
+        match migration.installed_on {
+            Some(installed_on) => println!(
+                "applied  {:>3}  {}  ({})",
+                migration.version, migration.description, installed_on
+            ),
+            None => println!(
+                "pending  {:>3}  {}",
+                migration.version, migration.description
+            ),
+        }
+    }
+    db_pool.close().await;
+    Ok(())
+}
+
+// Replays the archive into the history tables, touching only the database
+pub async fn reindex(scope: &ReindexScope) -> Result<()> {
+    let config = Config::from_env().context("Failed to load configuration")?;
+    let db_pool = init_db().await.context("Failed to initialize database")?;
+    let result = reindex::reindex(&db_pool, scope, midgard_api::finality_grace(&config))
+        .await
+        .context("Failed to reindex");
+    db_pool.close().await;
+    result
+}
+
+// Seeds the history tables from export files, touching only the database
+pub async fn import(
+    files: &[PathBuf],
+    resource: Resource,
+    pool: Option<String>,
+    interval: Interval,
+) -> Result<()> {
+    let pool = pool_for(resource, pool)?;
+    let config = Config::from_env().context("Failed to load configuration")?;
+    let finality_grace = midgard_api::finality_grace(&config);
+    let db_pool = init_db().await.context("Failed to initialize database")?;
+
+    let mut failed = 0;
+    for file in files {
+        let result = import::import_file(
+            &db_pool,
+            file,
+            resource,
+            pool.as_deref(),
+            interval,
+            finality_grace,
+        );
+        match result.await {
+            Ok(rows) => println!("Imported {} rows from {}", rows, file.display()),
+            Err(error) => {
+                eprintln!("Failed to import {} | {error:#}", file.display());
+                failed += 1;
+            }
+        }
+    }
+    db_pool.close().await;
+
+    if failed > 0 {
+        bail!("{} of {} files failed to import", failed, files.len());
+    }
+    Ok(())
+}
+
+// Depth is stored per pool and defaults to BTC.BTC, the others take no pool
+fn pool_for(resource: Resource, pool: Option<String>) -> Result<Option<String>> {
+    match (resource.is_per_pool(), pool) {
+        (true, pool) => Ok(Some(pool.unwrap_or_else(|| DEFAULT_POOL.to_string()))),
+        (false, None) => Ok(None),
+        (false, Some(_)) => bail!("{resource} is not stored per pool"),
+    }
+}
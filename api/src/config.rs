@@ -0,0 +1,272 @@
+use crate::models::{Interval, Resource};
+use crate::scheduler::Schedule;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use reqwest::Url;
+use std::{collections::HashMap, env, str::FromStr};
+use thiserror::Error;
+
+pub const DEFAULT_POOL: &str = "BTC.BTC";
+pub const DEFAULT_MIDGARD_URL: &str = "https://midgard.ninerealms.com/v2";
+pub const DEFAULT_SYNC_SCHEDULE: &str = "1h";
+pub const DEFAULT_BACKFILL_FROM: &str = "90d";
+// Start of Midgard history on mainnet
+pub const DEFAULT_MIDGARD_GENESIS: &str = "2021-04-01";
+// Most intervals Midgard returns per request
+pub const MAX_MIDGARD_COUNT: u16 = 400;
+pub const DEFAULT_LEADER_LOCK_KEY: i64 = 0x6d69_6467_6172_6400;
+pub const DEFAULT_USER_AGENT: &str =
+    concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("MIDGARD_POOLS is set but lists no pools")]
+    NoPools,
+
+    #[error("MIDGARD_URLS is set but lists no URLs")]
+    NoUrls,
+
+    #[error("Invalid value for {var}: {value}")]
+    InvalidValue { var: &'static str, value: String },
+}
+
+// How far back the first sync of a resource reaches
+#[derive(Clone, Copy, Debug)]
+pub enum BackfillStart {
+    Ago(Duration),
+    At(DateTime<Utc>),
+    Genesis,
+}
+
+impl BackfillStart {
+    pub fn resolve(&self, now: DateTime<Utc>, genesis: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            // Nothing is older than genesis, which also bounds very large day counts
+            BackfillStart::Ago(duration) => now
+                .checked_sub_signed(*duration)
+                .map_or(genesis, |time| time.max(genesis)),
+            BackfillStart::At(time) => *time,
+            BackfillStart::Genesis => genesis,
+        }
+    }
+}
+
+impl FromStr for BackfillStart {
+    type Err = String;
+
+    // `genesis`, a number of days back such as `90d`, or a date
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value == "genesis" {
+            return Ok(BackfillStart::Genesis);
+        }
+        if let Some(days) = value
+            .strip_suffix('d')
+            .and_then(|days| days.parse::<u32>().ok())
+        {
+            return Duration::try_days(i64::from(days))
+                .map(BackfillStart::Ago)
+                .ok_or_else(|| format!("Invalid backfill start: {}", value));
+        }
+        parse_time(value)
+            .map(BackfillStart::At)
+            .ok_or_else(|| format!("Invalid backfill start: {}", value))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    // Pools to ingest depth history for, or None to use the discovered catalog
+    pub pools: Option<Vec<String>>,
+    pub midgard_urls: Vec<Url>,
+    pub midgard_user_agent: String,
+    pub midgard_connect_timeout_secs: u64,
+    pub midgard_read_timeout_secs: u64,
+    pub midgard_timeout_secs: u64,
+    pub midgard_failover_cooldown_secs: u64,
+    pub midgard_max_retries: u32,
+    pub midgard_backoff_base_ms: u64,
+    pub midgard_backoff_max_ms: u64,
+    pub midgard_rate_limit_per_sec: f64,
+    pub midgard_rate_limit_burst: u32,
+    // How long after an interval closes Midgard is trusted to have indexed it
+    pub midgard_finality_grace_secs: u32,
+    // Bearer token for every /admin endpoint, which are disabled without it
+    pub admin_token: Option<String>,
+    pub sync_schedules: HashMap<Resource, Schedule>,
+    // Upper bound of the random delay added to every scheduled run
+    pub sync_jitter_secs: u64,
+    // Resources, pools and gaps fetched at the same time within one run
+    pub sync_concurrency: usize,
+    // Windows of one backfill fetched at the same time
+    pub backfill_concurrency: usize,
+    pub backfill_from: HashMap<Resource, BackfillStart>,
+    pub midgard_genesis: DateTime<Utc>,
+    // Intervals requested per Midgard call, at most MAX_MIDGARD_COUNT
+    pub midgard_max_count: u16,
+    // History older than backfill_hourly_days is fetched at this resolution
+    pub backfill_coarse_interval: Option<Interval>,
+    pub backfill_hourly_days: u32,
+    // Postgres advisory lock key shared by every replica of this deployment
+    pub leader_lock_key: i64,
+    pub leader_check_interval_secs: u64,
+    // Deadline for in-flight ingestion and the database pool on shutdown
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let pools = match env::var("MIDGARD_POOLS") {
+            Ok(value) => {
+                let pools = parse_list(&value);
+                if pools.is_empty() {
+                    return Err(ConfigError::NoPools);
+                }
+                Some(pools)
+            }
+            Err(_) => None,
+        };
+
+        let midgard_urls = parse_list(
+            &env::var("MIDGARD_URLS").unwrap_or_else(|_| DEFAULT_MIDGARD_URL.to_string()),
+        )
+        .into_iter()
+        .map(|url| {
+            Url::parse(&url).map_err(|_| ConfigError::InvalidValue {
+                var: "MIDGARD_URLS",
+                value: url,
+            })
+        })
+        .collect::<Result<Vec<Url>, ConfigError>>()?;
+        if midgard_urls.is_empty() {
+            return Err(ConfigError::NoUrls);
+        }
+
+        // SYNC_SCHEDULE applies to every resource unless overridden per resource
+        let sync_schedules =
+            parse_per_resource("SYNC_SCHEDULE", DEFAULT_SYNC_SCHEDULE, schedule_var)?;
+        let backfill_from =
+            parse_per_resource("BACKFILL_FROM", DEFAULT_BACKFILL_FROM, backfill_from_var)?;
+
+        let midgard_genesis =
+            env::var("MIDGARD_GENESIS").unwrap_or_else(|_| DEFAULT_MIDGARD_GENESIS.to_string());
+        let midgard_genesis = parse_time(&midgard_genesis).ok_or(ConfigError::InvalidValue {
+            var: "MIDGARD_GENESIS",
+            value: midgard_genesis,
+        })?;
+
+        let backfill_coarse_interval = match env::var("BACKFILL_COARSE_INTERVAL") {
+            Ok(value) => Some(
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| ConfigError::InvalidValue {
+                        var: "BACKFILL_COARSE_INTERVAL",
+                        value,
+                    })?,
+            ),
+            Err(_) => None,
+        };
+
+        Ok(Self {
+            pools,
+            midgard_urls,
+            midgard_user_agent: env::var("MIDGARD_USER_AGENT")
+                .unwrap_or_else(|_| DEFAULT_USER_AGENT.to_string()),
+            midgard_connect_timeout_secs: parse_var("MIDGARD_CONNECT_TIMEOUT_SECS", 10)?,
+            midgard_read_timeout_secs: parse_var("MIDGARD_READ_TIMEOUT_SECS", 30)?,
+            midgard_timeout_secs: parse_var("MIDGARD_TIMEOUT_SECS", 60)?,
+            midgard_failover_cooldown_secs: parse_var("MIDGARD_FAILOVER_COOLDOWN_SECS", 60)?,
+            midgard_max_retries: parse_var("MIDGARD_MAX_RETRIES", 3)?,
+            midgard_backoff_base_ms: parse_var("MIDGARD_BACKOFF_BASE_MS", 500)?,
+            midgard_backoff_max_ms: parse_var("MIDGARD_BACKOFF_MAX_MS", 30_000)?,
+            midgard_rate_limit_per_sec: parse_var("MIDGARD_RATE_LIMIT_PER_SEC", 2.0)?,
+            midgard_rate_limit_burst: parse_var("MIDGARD_RATE_LIMIT_BURST", 5)?,
+            midgard_finality_grace_secs: parse_var("MIDGARD_FINALITY_GRACE_SECS", 300)?,
+            admin_token: env::var("ADMIN_TOKEN")
+                .ok()
+                .filter(|token| !token.trim().is_empty()),
+            sync_schedules,
+            sync_jitter_secs: parse_var("SYNC_JITTER_SECS", 30)?,
+            sync_concurrency: parse_var("SYNC_CONCURRENCY", 4)?.max(1),
+            backfill_concurrency: parse_var("BACKFILL_CONCURRENCY", 4)?.max(1),
+            backfill_from,
+            midgard_genesis,
+            midgard_max_count: parse_var("MIDGARD_MAX_COUNT", MAX_MIDGARD_COUNT)?
+                .clamp(1, MAX_MIDGARD_COUNT),
+            backfill_coarse_interval,
+            backfill_hourly_days: parse_var("BACKFILL_HOURLY_DAYS", 90)?,
+            leader_lock_key: parse_var("LEADER_LOCK_KEY", DEFAULT_LEADER_LOCK_KEY)?,
+            leader_check_interval_secs: parse_var("LEADER_CHECK_INTERVAL_SECS", 10)?,
+            shutdown_timeout_secs: parse_var("SHUTDOWN_TIMEOUT_SECS", 30)?,
+        })
+    }
+}
+
+fn schedule_var(resource: Resource) -> &'static str {
+    match resource {
+        Resource::Depth => "SYNC_SCHEDULE_DEPTH",
+        Resource::Earnings => "SYNC_SCHEDULE_EARNINGS",
+        Resource::RunePool => "SYNC_SCHEDULE_RUNE_POOL",
+        Resource::Swaps => "SYNC_SCHEDULE_SWAPS",
+    }
+}
+
+fn backfill_from_var(resource: Resource) -> &'static str {
+    match resource {
+        Resource::Depth => "BACKFILL_FROM_DEPTH",
+        Resource::Earnings => "BACKFILL_FROM_EARNINGS",
+        Resource::RunePool => "BACKFILL_FROM_RUNE_POOL",
+        Resource::Swaps => "BACKFILL_FROM_SWAPS",
+    }
+}
+
+// Reads `var` as the default for every resource, then the per-resource overrides
+fn parse_per_resource<T: FromStr + Clone>(
+    var: &'static str,
+    default: &str,
+    resource_var: fn(Resource) -> &'static str,
+) -> Result<HashMap<Resource, T>, ConfigError> {
+    let default = match default.parse() {
+        Ok(default) => default,
+        Err(_) => panic!("Invalid default for {var}: {default}"),
+    };
+    let default: T = parse_var(var, default)?;
+
+    Resource::ALL
+        .into_iter()
+        .map(|resource| {
+            parse_var(resource_var(resource), default.clone()).map(|value| (resource, value))
+        })
+        .collect()
+}
+
+// An RFC 3339 timestamp or a plain date at midnight UTC
+pub fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|time| time.and_utc())
+        })
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn parse_var<T: FromStr>(var: &'static str, default: T) -> Result<T, ConfigError> {
+    match env::var(var) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| ConfigError::InvalidValue { var, value }),
+        Err(_) => Ok(default),
+    }
+}
@@ -1,9 +1,17 @@
 use anyhow::Result;
-use sqlx::{postgres::PgPoolOptions, PgPool};
-use std::env;
+use chrono::{DateTime, Utc};
+use sqlx::{migrate::Migrator, postgres::PgPoolOptions, PgPool};
+use std::{collections::HashMap, env};
 use thiserror::Error;
 
+pub mod backfill_windows;
+pub mod gaps;
+pub mod history_meta;
 pub mod insertions;
+pub mod raw_responses;
+pub mod sync_runs;
+pub mod sync_state;
+pub mod upstream_errors;
 
 #[derive(Error, Debug)]
 pub enum DbError {
@@ -17,7 +25,16 @@ pub enum DbError {
     Migrate(#[from] sqlx::migrate::MigrateError),
 }
 
-pub async fn init_db() -> Result<PgPool, DbError> {
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+// A migration shipped with the binary and when it was applied, if it was
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub installed_on: Option<DateTime<Utc>>,
+}
+
+pub async fn connect_db() -> Result<PgPool, DbError> {
     let db_url = env::var("DATABASE_URL")?;
 
     let pool = PgPoolOptions::new()
@@ -25,7 +42,37 @@ pub async fn init_db() -> Result<PgPool, DbError> {
         .connect(&db_url)
         .await?;
 
-    sqlx::migrate!("./migrations").run(&pool).await?;
+    Ok(pool)
+}
+
+pub async fn init_db() -> Result<PgPool, DbError> {
+    let pool = connect_db().await?;
+    MIGRATOR.run(&pool).await?;
 
     Ok(pool)
 }
+
+// Reads the applied versions without creating the bookkeeping table on a fresh database
+pub async fn migration_status(pool: &PgPool) -> Result<Vec<MigrationStatus>, DbError> {
+    let has_table: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(pool)
+        .await?;
+    let applied: HashMap<i64, DateTime<Utc>> = if has_table {
+        sqlx::query_as("SELECT version, installed_on FROM _sqlx_migrations WHERE success")
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    Ok(MIGRATOR
+        .iter()
+        .map(|migration| MigrationStatus {
+            version: migration.version,
+            description: migration.description.to_string(),
+            installed_on: applied.get(&migration.version).copied(),
+        })
+        .collect())
+}
@@ -0,0 +1,90 @@
+use crate::models::{Interval, Resource};
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgExecutor, PgPool};
+
+#[derive(Debug, FromRow)]
+pub struct BackfillWindow {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub interval: String,
+    pub completed: bool,
+}
+
+pub async fn get_backfill_windows(
+    pool: &PgPool,
+    resource: Resource,
+    pool_asset: Option<&str>,
+) -> Result<Vec<BackfillWindow>, sqlx::Error> {
+    sqlx::query_as::<_, BackfillWindow>(
+        "SELECT start_time, end_time, interval, completed_at IS NOT NULL AS completed
+         FROM backfill_windows
+         WHERE resource = $1 AND pool = $2
+         ORDER BY start_time",
+    )
+    .bind(resource.as_str())
+    .bind(pool_asset.unwrap_or(""))
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn plan_backfill_windows(
+    pool: &PgPool,
+    resource: Resource,
+    pool_asset: Option<&str>,
+    windows: &[(DateTime<Utc>, DateTime<Utc>, Interval)],
+) -> Result<(), sqlx::Error> {
+    let start_times: Vec<DateTime<Utc>> = windows.iter().map(|window| window.0).collect();
+    let end_times: Vec<DateTime<Utc>> = windows.iter().map(|window| window.1).collect();
+    let intervals: Vec<&str> = windows.iter().map(|window| window.2.as_str()).collect();
+
+    sqlx::query(
+        "INSERT INTO backfill_windows (resource, pool, start_time, end_time, interval)
+         SELECT $1, $2, * FROM UNNEST($3::TIMESTAMPTZ[], $4::TIMESTAMPTZ[], $5::TEXT[])
+         ON CONFLICT (resource, pool, start_time) DO NOTHING",
+    )
+    .bind(resource.as_str())
+    .bind(pool_asset.unwrap_or(""))
+    .bind(&start_times)
+    .bind(&end_times)
+    .bind(&intervals)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn complete_backfill_window<'e, E>(
+    executor: E,
+    resource: Resource,
+    pool_asset: Option<&str>,
+    start_time: DateTime<Utc>,
+) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query(
+        "UPDATE backfill_windows SET completed_at = now()
+         WHERE resource = $1 AND pool = $2 AND start_time = $3",
+    )
+    .bind(resource.as_str())
+    .bind(pool_asset.unwrap_or(""))
+    .bind(start_time)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+pub async fn clear_backfill_windows<'e, E>(
+    executor: E,
+    resource: Resource,
+    pool_asset: Option<&str>,
+) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query("DELETE FROM backfill_windows WHERE resource = $1 AND pool = $2")
+        .bind(resource.as_str())
+        .bind(pool_asset.unwrap_or(""))
+        .execute(executor)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use crate::models::{Gap, Resource};
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+
+#[derive(FromRow)]
+struct GapRow {
+    pool: Option<String>,
+    interval: String,
+    gap_start: DateTime<Utc>,
+    gap_end: DateTime<Utc>,
+    missing_intervals: i64,
+}
+
+// Finds holes between consecutive intervals of a history table. A gap is
+// measured at the resolution of the row before it, which may be coarser than an hour
+pub async fn find_gaps(pool: &PgPool, resource: Resource) -> Result<Vec<Gap>, sqlx::Error> {
+    let pool_column = if resource.is_per_pool() {
+        "pool"
+    } else {
+        "NULL::TEXT"
+    };
+
+    let rows: Vec<GapRow> = sqlx::query_as(&format!(
+        "SELECT pool, interval, gap_start, gap_end,
+                CEIL(EXTRACT(EPOCH FROM gap_end - gap_start) / EXTRACT(EPOCH FROM ('1 ' || interval)::INTERVAL))::BIGINT AS missing_intervals
+         FROM (
+             SELECT {pool_column} AS pool,
+                    interval,
+                    end_time AS gap_start,
+                    LEAD(start_time) OVER (PARTITION BY {pool_column} ORDER BY start_time) AS gap_end
+             FROM {table}
+         ) intervals
+         WHERE gap_end > gap_start
+         ORDER BY pool, gap_start",
+        pool_column = pool_column,
+        table = resource.as_str()
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    let gaps = rows
+        .into_iter()
+        .map(|row| Gap {
+            resource,
+            pool: row.pool,
+            interval: row.interval,
+            start_time: row.gap_start,
+            end_time: row.gap_end,
+            missing_intervals: row.missing_intervals,
+        })
+        .collect();
+
+    Ok(gaps)
+}
+
+// Start of the oldest stored interval, where history already reaches back to
+pub async fn get_first_start_time(
+    pool: &PgPool,
+    resource: Resource,
+    pool_asset: Option<&str>,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let pool_filter = if resource.is_per_pool() {
+        "pool = $1"
+    } else {
+        "$1::TEXT IS NULL"
+    };
+
+    sqlx::query_scalar(&format!(
+        "SELECT MIN(start_time) FROM {table} WHERE {pool_filter}",
+        table = resource.as_str(),
+        pool_filter = pool_filter
+    ))
+    .bind(pool_asset)
+    .fetch_one(pool)
+    .await
+}
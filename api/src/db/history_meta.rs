@@ -0,0 +1,67 @@
+use crate::models::{HistoryMeta, Resource};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::{types::Json, Executor, PgPool, Postgres};
+
+// Keeps the latest meta of each fetch window. Windows without a readable
+// range are skipped, Midgard reports it as epoch seconds in strings
+pub async fn upsert_history_meta<'e>(
+    executor: impl Executor<'e, Database = Postgres>,
+    resource: Resource,
+    pool: Option<&str>,
+    interval: &str,
+    meta: &Value,
+    fetched_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    let (Some(start_time), Some(end_time)) =
+        (meta_time(meta, "startTime"), meta_time(meta, "endTime"))
+    else {
+        return Ok(());
+    };
+
+    sqlx::query(
+        "INSERT INTO history_meta (resource, pool, interval, start_time, end_time, meta, fetched_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         ON CONFLICT (resource, pool, interval, start_time) DO UPDATE SET
+             end_time = EXCLUDED.end_time,
+             meta = EXCLUDED.meta,
+             fetched_at = EXCLUDED.fetched_at",
+    )
+    .bind(resource.as_str())
+    .bind(pool.unwrap_or(""))
+    .bind(interval)
+    .bind(start_time)
+    .bind(end_time)
+    .bind(Json(meta))
+    .bind(fetched_at)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+// Meta of the windows overlapping [start_time, end_time)
+pub async fn get_history_meta(
+    pool: &PgPool,
+    resource: Resource,
+    pool_asset: Option<&str>,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Result<Vec<HistoryMeta>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT NULLIF(pool, '') AS pool, interval, start_time, end_time, fetched_at, meta
+         FROM history_meta
+         WHERE resource = $1 AND pool = $2 AND start_time < $4 AND end_time > $3
+         ORDER BY start_time, interval",
+    )
+    .bind(resource.as_str())
+    .bind(pool_asset.unwrap_or(""))
+    .bind(start_time)
+    .bind(end_time)
+    .fetch_all(pool)
+    .await
+}
+
+fn meta_time(meta: &Value, field: &str) -> Option<DateTime<Utc>> {
+    let seconds = meta.get(field)?.as_str()?.parse().ok()?;
+    DateTime::from_timestamp(seconds, 0)
+}
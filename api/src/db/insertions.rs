@@ -1,183 +1,597 @@
-use crate::models::{DepthPriceHistory, EarningsHistory, RunePoolHistory, SwapsHistory};
+use crate::models::{
+    DepthPriceHistory, EarningsHistory, Pool, PoolEarnings, RunePoolHistory, SwapsHistory,
+};
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashMap;
 
-pub async fn get_last_end_time(
-    pool: &PgPool,
-    table: &str,
-) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
-    let last_end_time: Option<DateTime<Utc>> =
-        sqlx::query_scalar(&format!("SELECT max(end_time) FROM {}", table))
-            .fetch_optional(pool)
+pub async fn get_available_pools(pool: &PgPool) -> Result<Vec<String>, sqlx::Error> {
+    let assets: Vec<String> =
+        sqlx::query_scalar("SELECT asset FROM pools WHERE status = 'available' ORDER BY asset")
+            .fetch_all(pool)
             .await?;
-    Ok(last_end_time)
+    Ok(assets)
+}
+
+// Any status, removed pools keep their history upstream
+pub async fn is_catalog_pool(pool: &PgPool, asset: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pools WHERE asset = $1)")
+        .bind(asset)
+        .fetch_one(pool)
+        .await
+}
+
+// Collects one field of every item into an array for UNNEST
+fn column<T, U>(data: &[T], field: impl Fn(&T) -> U) -> Vec<U> {
+    data.iter().map(field).collect()
+}
+
+// Rows stored at another resolution that overlap the new intervals would count
+// the same range twice, so they are deleted before the upsert. Earnings pool rows
+// go with their parent through the cascade
+async fn delete_overlapping(
+    conn: &mut PgConnection,
+    table: &str,
+    pools: Option<Vec<String>>,
+    start_times: Vec<DateTime<Utc>>,
+    end_times: Vec<DateTime<Utc>>,
+    intervals: Vec<String>,
+) -> Result<(), sqlx::Error> {
+    let pool_filter = match pools {
+        Some(_) => "AND h.pool = n.pool",
+        None => "",
+    };
+    sqlx::query(&format!(
+        "DELETE FROM {table} h
+         USING UNNEST($1::TIMESTAMPTZ[], $2::TIMESTAMPTZ[], $3::TEXT[], $4::TEXT[])
+               AS n(start_time, end_time, interval, pool)
+         WHERE h.interval <> n.interval
+           AND h.start_time < n.end_time
+           AND h.end_time > n.start_time
+           {pool_filter}"
+    ))
+    .bind(&start_times)
+    .bind(end_times)
+    .bind(intervals)
+    .bind(pools.unwrap_or_else(|| vec![String::new(); start_times.len()]))
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+// Stores a catalog snapshot. Pools missing from it were dropped upstream and
+// are marked `removed`, which keeps them out of depth ingestion
+pub async fn upsert_pools(
+    conn: &mut PgConnection,
+    data: &[Pool],
+    seen_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    println!("Upserting pools...\n");
+    // An empty snapshot is more likely an upstream glitch than every pool gone
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    sqlx::query(
+        "UPDATE pools SET status = 'removed'
+         WHERE status <> 'removed' AND asset <> ALL($1::TEXT[])",
+    )
+    .bind(column(data, |item| item.asset.clone()))
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query(
+        "INSERT INTO pools (asset, status, first_seen, last_seen)
+         SELECT asset, status, $3, $3 FROM UNNEST($1::TEXT[], $2::TEXT[]) AS p(asset, status)
+         ON CONFLICT (asset) DO UPDATE SET status = EXCLUDED.status, last_seen = EXCLUDED.last_seen",
+    )
+    .bind(column(data, |item| item.asset.clone()))
+    .bind(column(data, |item| item.status.clone()))
+    .bind(seen_at)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
 }
 
 pub async fn insert_depth_price_history(
-    pool: &PgPool,
-    data: &Vec<DepthPriceHistory>,
+    conn: &mut PgConnection,
+    data: &[DepthPriceHistory],
 ) -> Result<(), sqlx::Error> {
-    println!("Inserting depth price history...\n");
-    for item in data {
-        sqlx::query(
-            "INSERT INTO depth_price_history (start_time, end_time, asset_depth, rune_depth, asset_price, asset_price_usd, liquidity_units, members_count, synth_units, synth_supply, units, luvi) 
-             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
-        )
-        .bind(item.start_time)
-        .bind(item.end_time)
-        .bind(item.asset_depth)
-        .bind(item.rune_depth)
-        .bind(&item.asset_price)
-        .bind(&item.asset_price_usd)
-        .bind(item.liquidity_units)
-        .bind(item.members_count)
-        .bind(item.synth_units)
-        .bind(item.synth_supply)
-        .bind(item.units)
-        .bind(&item.luvi)
-        .execute(pool)
-        .await?;
+    println!("Upserting depth price history...\n");
+    if data.is_empty() {
+        return Ok(());
     }
+
+    delete_overlapping(
+        conn,
+        "depth_price_history",
+        Some(column(data, |item| item.pool.clone())),
+        column(data, |item| item.start_time),
+        column(data, |item| item.end_time),
+        column(data, |item| item.interval.clone()),
+    )
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO depth_price_history (pool, start_time, end_time, asset_depth, rune_depth, asset_price, asset_price_usd, liquidity_units, members_count, synth_units, synth_supply, units, luvi, is_final, interval)
+         SELECT * FROM UNNEST(
+                $1::TEXT[],
+                $2::TIMESTAMPTZ[],
+                $3::TIMESTAMPTZ[],
+                $4::BIGINT[],
+                $5::BIGINT[],
+                $6::NUMERIC[],
+                $7::NUMERIC[],
+                $8::BIGINT[],
+                $9::BIGINT[],
+                $10::BIGINT[],
+                $11::BIGINT[],
+                $12::BIGINT[],
+                $13::NUMERIC[],
+                $14::BOOLEAN[],
+                $15::TEXT[]
+         )
+         ON CONFLICT (pool, start_time) DO UPDATE SET
+                end_time = EXCLUDED.end_time,
+                asset_depth = EXCLUDED.asset_depth,
+                rune_depth = EXCLUDED.rune_depth,
+                asset_price = EXCLUDED.asset_price,
+                asset_price_usd = EXCLUDED.asset_price_usd,
+                liquidity_units = EXCLUDED.liquidity_units,
+                members_count = EXCLUDED.members_count,
+                synth_units = EXCLUDED.synth_units,
+                synth_supply = EXCLUDED.synth_supply,
+                units = EXCLUDED.units,
+                luvi = EXCLUDED.luvi,
+                is_final = EXCLUDED.is_final,
+                interval = EXCLUDED.interval",
+    )
+    .bind(column(data, |item| item.pool.clone()))
+    .bind(column(data, |item| item.start_time))
+    .bind(column(data, |item| item.end_time))
+    .bind(column(data, |item| item.asset_depth))
+    .bind(column(data, |item| item.rune_depth))
+    .bind(column(data, |item| item.asset_price.clone()))
+    .bind(column(data, |item| item.asset_price_usd.clone()))
+    .bind(column(data, |item| item.liquidity_units))
+    .bind(column(data, |item| item.members_count))
+    .bind(column(data, |item| item.synth_units))
+    .bind(column(data, |item| item.synth_supply))
+    .bind(column(data, |item| item.units))
+    .bind(column(data, |item| item.luvi.clone()))
+    .bind(column(data, |item| item.is_final))
+    .bind(column(data, |item| item.interval.clone()))
+    .execute(&mut *conn)
+    .await?;
     Ok(())
 }
 
 pub async fn insert_earnings_history(
-    pool: &PgPool,
-    data: &Vec<EarningsHistory>,
+    conn: &mut PgConnection,
+    data: &[EarningsHistory],
 ) -> Result<(), sqlx::Error> {
-    println!("Inserting earnings history...\n");
-    for item in data {
-        let earnings_history_id: (i32,) = sqlx::query_as(
-            "INSERT INTO earnings_history (start_time, end_time, liquidity_fees, block_rewards, earnings, bonding_earnings, liquidity_earnings, avg_node_count, rune_price_usd) 
-             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING id",
-        )
-        .bind(item.start_time)
-        .bind(item.end_time)
-        .bind(item.liquidity_fees)
-        .bind(item.block_rewards)
-        .bind(item.earnings)
-        .bind(item.bonding_earnings)
-        .bind(item.liquidity_earnings)
-        .bind(&item.avg_node_count)
-        .bind(&item.rune_price_usd)
-        .fetch_one(pool)
-        .await?;
-
-        for pool_earning in &item.pools {
-            sqlx::query(
-                "INSERT INTO pool_earnings (earnings_history_id, pool, asset_liquidity_fees, rune_liquidity_fees, total_liquidity_fees_rune, saver_earning, rewards, earnings) 
-                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
-            )
-            .bind(earnings_history_id.0)
-            .bind(&pool_earning.pool)
-            .bind(pool_earning.asset_liquidity_fees)
-            .bind(pool_earning.rune_liquidity_fees)
-            .bind(pool_earning.total_liquidity_fees_rune)
-            .bind(pool_earning.saver_earning)
-            .bind(pool_earning.rewards)
-            .bind(pool_earning.earnings)
-            .execute(pool)
-            .await?;
-        }
+    println!("Upserting earnings history...\n");
+    if data.is_empty() {
+        return Ok(());
     }
+
+    delete_overlapping(
+        conn,
+        "earnings_history",
+        None,
+        column(data, |item| item.start_time),
+        column(data, |item| item.end_time),
+        column(data, |item| item.interval.clone()),
+    )
+    .await?;
+
+    let ids: Vec<(i32, DateTime<Utc>)> = sqlx::query_as(
+        "INSERT INTO earnings_history (start_time, end_time, liquidity_fees, block_rewards, earnings, bonding_earnings, liquidity_earnings, avg_node_count, rune_price_usd, is_final, interval)
+         SELECT * FROM UNNEST(
+                $1::TIMESTAMPTZ[],
+                $2::TIMESTAMPTZ[],
+                $3::BIGINT[],
+                $4::BIGINT[],
+                $5::BIGINT[],
+                $6::BIGINT[],
+                $7::BIGINT[],
+                $8::NUMERIC[],
+                $9::NUMERIC[],
+                $10::BOOLEAN[],
+                $11::TEXT[]
+         )
+         ON CONFLICT (start_time) DO UPDATE SET
+                end_time = EXCLUDED.end_time,
+                liquidity_fees = EXCLUDED.liquidity_fees,
+                block_rewards = EXCLUDED.block_rewards,
+                earnings = EXCLUDED.earnings,
+                bonding_earnings = EXCLUDED.bonding_earnings,
+                liquidity_earnings = EXCLUDED.liquidity_earnings,
+                avg_node_count = EXCLUDED.avg_node_count,
+                rune_price_usd = EXCLUDED.rune_price_usd,
+                is_final = EXCLUDED.is_final,
+                interval = EXCLUDED.interval
+         RETURNING id, start_time",
+    )
+    .bind(column(data, |item| item.start_time))
+    .bind(column(data, |item| item.end_time))
+    .bind(column(data, |item| item.liquidity_fees))
+    .bind(column(data, |item| item.block_rewards))
+    .bind(column(data, |item| item.earnings))
+    .bind(column(data, |item| item.bonding_earnings))
+    .bind(column(data, |item| item.liquidity_earnings))
+    .bind(column(data, |item| item.avg_node_count.clone()))
+    .bind(column(data, |item| item.rune_price_usd.clone()))
+    .bind(column(data, |item| item.is_final))
+    .bind(column(data, |item| item.interval.clone()))
+    .fetch_all(&mut *conn)
+    .await?;
+
+    // Pair every pool row with the id of its parent interval
+    let ids: HashMap<DateTime<Utc>, i32> = ids
+        .into_iter()
+        .map(|(id, start_time)| (start_time, id))
+        .collect();
+    let pool_earnings: Vec<(i32, &PoolEarnings)> = data
+        .iter()
+        .flat_map(|item| {
+            let id = ids[&item.start_time];
+            item.pools
+                .iter()
+                .map(move |pool_earning| (id, pool_earning))
+        })
+        .collect();
+    if pool_earnings.is_empty() {
+        return Ok(());
+    }
+
+    sqlx::query(
+        "INSERT INTO pool_earnings (earnings_history_id, pool, asset_liquidity_fees, rune_liquidity_fees, total_liquidity_fees_rune, saver_earning, rewards, earnings)
+         SELECT * FROM UNNEST(
+                $1::INT[],
+                $2::TEXT[],
+                $3::BIGINT[],
+                $4::BIGINT[],
+                $5::BIGINT[],
+                $6::BIGINT[],
+                $7::BIGINT[],
+                $8::BIGINT[]
+         )
+         ON CONFLICT (earnings_history_id, pool) DO UPDATE SET
+                asset_liquidity_fees = EXCLUDED.asset_liquidity_fees,
+                rune_liquidity_fees = EXCLUDED.rune_liquidity_fees,
+                total_liquidity_fees_rune = EXCLUDED.total_liquidity_fees_rune,
+                saver_earning = EXCLUDED.saver_earning,
+                rewards = EXCLUDED.rewards,
+                earnings = EXCLUDED.earnings",
+    )
+    .bind(column(&pool_earnings, |(id, _)| *id))
+    .bind(column(&pool_earnings, |(_, pool_earning)| pool_earning.pool.clone()))
+    .bind(column(&pool_earnings, |(_, pool_earning)| pool_earning.asset_liquidity_fees))
+    .bind(column(&pool_earnings, |(_, pool_earning)| pool_earning.rune_liquidity_fees))
+    .bind(column(&pool_earnings, |(_, pool_earning)| pool_earning.total_liquidity_fees_rune))
+    .bind(column(&pool_earnings, |(_, pool_earning)| pool_earning.saver_earning))
+    .bind(column(&pool_earnings, |(_, pool_earning)| pool_earning.rewards))
+    .bind(column(&pool_earnings, |(_, pool_earning)| pool_earning.earnings))
+    .execute(&mut *conn)
+    .await?;
     Ok(())
 }
 
 pub async fn insert_rune_pool_history(
-    pool: &PgPool,
-    data: &Vec<RunePoolHistory>,
+    conn: &mut PgConnection,
+    data: &[RunePoolHistory],
 ) -> Result<(), sqlx::Error> {
-    println!("Inserting rune pool history...\n");
-    for item in data {
-        sqlx::query(
-            "INSERT INTO rune_pool_history (start_time, end_time, count, units) 
-             VALUES ($1, $2, $3, $4)",
-        )
-        .bind(item.start_time)
-        .bind(item.end_time)
-        .bind(item.count)
-        .bind(item.units)
-        .execute(pool)
-        .await?;
+    println!("Upserting rune pool history...\n");
+    if data.is_empty() {
+        return Ok(());
     }
+
+    delete_overlapping(
+        conn,
+        "rune_pool_history",
+        None,
+        column(data, |item| item.start_time),
+        column(data, |item| item.end_time),
+        column(data, |item| item.interval.clone()),
+    )
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO rune_pool_history (start_time, end_time, count, units, is_final, interval)
+         SELECT * FROM UNNEST(
+                $1::TIMESTAMPTZ[],
+                $2::TIMESTAMPTZ[],
+                $3::BIGINT[],
+                $4::BIGINT[],
+                $5::BOOLEAN[],
+                $6::TEXT[]
+         )
+         ON CONFLICT (start_time) DO UPDATE SET
+                end_time = EXCLUDED.end_time,
+                count = EXCLUDED.count,
+                units = EXCLUDED.units,
+                is_final = EXCLUDED.is_final,
+                interval = EXCLUDED.interval",
+    )
+    .bind(column(data, |item| item.start_time))
+    .bind(column(data, |item| item.end_time))
+    .bind(column(data, |item| item.count))
+    .bind(column(data, |item| item.units))
+    .bind(column(data, |item| item.is_final))
+    .bind(column(data, |item| item.interval.clone()))
+    .execute(&mut *conn)
+    .await?;
     Ok(())
 }
 
 pub async fn insert_swaps_history(
-    pool: &PgPool,
-    data: &Vec<SwapsHistory>,
+    conn: &mut PgConnection,
+    data: &[SwapsHistory],
 ) -> Result<(), sqlx::Error> {
-    println!("Inserting swaps history...\n");
-    for item in data {
-        sqlx::query(
-            "INSERT INTO swaps_history (
-                start_time, end_time, 
-                to_asset_count, to_rune_count, to_trade_count, from_trade_count, 
-                synth_mint_count, synth_redeem_count, total_count,
-                to_asset_volume, to_rune_volume, to_trade_volume, from_trade_volume,
-                synth_mint_volume, synth_redeem_volume, total_volume,
-                to_asset_volume_usd, to_rune_volume_usd, to_trade_volume_usd, from_trade_volume_usd,
-                synth_mint_volume_usd, synth_redeem_volume_usd, total_volume_usd,
-                to_asset_fees, to_rune_fees, to_trade_fees, from_trade_fees,
-                synth_mint_fees, synth_redeem_fees, total_fees,
-                to_asset_average_slip, to_rune_average_slip, to_trade_average_slip, from_trade_average_slip,
-                synth_mint_average_slip, synth_redeem_average_slip, average_slip,
-                rune_price_usd
-            ) VALUES (
-                $1, $2, 
-                $3, $4, $5, $6, 
-                $7, $8, $9, 
-                $10, $11, $12, $13, 
-                $14, $15, $16, 
-                $17, $18, $19, $20, 
-                $21, $22, $23, 
-                $24, $25, $26, $27, 
-                $28, $29, $30, 
-                $31, $32, $33, $34, 
-                $35, $36, $37, 
-                $38
-            )"
-        )
-        .bind(item.start_time)
-        .bind(item.end_time)
-        .bind(item.to_asset_count)
-        .bind(item.to_rune_count)
-        .bind(item.to_trade_count)
-        .bind(item.from_trade_count)
-        .bind(item.synth_mint_count)
-        .bind(item.synth_redeem_count)
-        .bind(item.total_count)
-        .bind(item.to_asset_volume)
-        .bind(item.to_rune_volume)
-        .bind(item.to_trade_volume)
-        .bind(item.from_trade_volume)
-        .bind(item.synth_mint_volume)
-        .bind(item.synth_redeem_volume)
-        .bind(item.total_volume)
-        .bind(item.to_asset_volume_usd)
-        .bind(item.to_rune_volume_usd)
-        .bind(item.to_trade_volume_usd)
-        .bind(item.from_trade_volume_usd)
-        .bind(item.synth_mint_volume_usd)
-        .bind(item.synth_redeem_volume_usd)
-        .bind(item.total_volume_usd)
-        .bind(item.to_asset_fees)
-        .bind(item.to_rune_fees)
-        .bind(item.to_trade_fees)
-        .bind(item.from_trade_fees)
-        .bind(item.synth_mint_fees)
-        .bind(item.synth_redeem_fees)
-        .bind(item.total_fees)
-        .bind(&item.to_asset_average_slip)
-        .bind(&item.to_rune_average_slip)
-        .bind(&item.to_trade_average_slip)
-        .bind(&item.from_trade_average_slip)
-        .bind(&item.synth_mint_average_slip)
-        .bind(&item.synth_redeem_average_slip)
-        .bind(&item.average_slip)
-        .bind(&item.rune_price_usd)
-        .execute(pool)
-        .await?;
+    println!("Upserting swaps history...\n");
+    if data.is_empty() {
+        return Ok(());
     }
+
+    delete_overlapping(
+        conn,
+        "swaps_history",
+        None,
+        column(data, |item| item.start_time),
+        column(data, |item| item.end_time),
+        column(data, |item| item.interval.clone()),
+    )
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO swaps_history (start_time, end_time, to_asset_count, to_rune_count, to_trade_count, from_trade_count, synth_mint_count, synth_redeem_count, total_count, to_asset_volume, to_rune_volume, to_trade_volume, from_trade_volume, synth_mint_volume, synth_redeem_volume, total_volume, to_asset_volume_usd, to_rune_volume_usd, to_trade_volume_usd, from_trade_volume_usd, synth_mint_volume_usd, synth_redeem_volume_usd, total_volume_usd, to_asset_fees, to_rune_fees, to_trade_fees, from_trade_fees, synth_mint_fees, synth_redeem_fees, total_fees, to_asset_average_slip, to_rune_average_slip, to_trade_average_slip, from_trade_average_slip, synth_mint_average_slip, synth_redeem_average_slip, average_slip, rune_price_usd, is_final, interval)
+         SELECT * FROM UNNEST(
+                $1::TIMESTAMPTZ[],
+                $2::TIMESTAMPTZ[],
+                $3::BIGINT[],
+                $4::BIGINT[],
+                $5::BIGINT[],
+                $6::BIGINT[],
+                $7::BIGINT[],
+                $8::BIGINT[],
+                $9::BIGINT[],
+                $10::BIGINT[],
+                $11::BIGINT[],
+                $12::BIGINT[],
+                $13::BIGINT[],
+                $14::BIGINT[],
+                $15::BIGINT[],
+                $16::BIGINT[],
+                $17::BIGINT[],
+                $18::BIGINT[],
+                $19::BIGINT[],
+                $20::BIGINT[],
+                $21::BIGINT[],
+                $22::BIGINT[],
+                $23::BIGINT[],
+                $24::BIGINT[],
+                $25::BIGINT[],
+                $26::BIGINT[],
+                $27::BIGINT[],
+                $28::BIGINT[],
+                $29::BIGINT[],
+                $30::BIGINT[],
+                $31::NUMERIC[],
+                $32::NUMERIC[],
+                $33::NUMERIC[],
+                $34::NUMERIC[],
+                $35::NUMERIC[],
+                $36::NUMERIC[],
+                $37::NUMERIC[],
+                $38::NUMERIC[],
+                $39::BOOLEAN[],
+                $40::TEXT[]
+         )
+         ON CONFLICT (start_time) DO UPDATE SET
+                end_time = EXCLUDED.end_time,
+                to_asset_count = EXCLUDED.to_asset_count,
+                to_rune_count = EXCLUDED.to_rune_count,
+                to_trade_count = EXCLUDED.to_trade_count,
+                from_trade_count = EXCLUDED.from_trade_count,
+                synth_mint_count = EXCLUDED.synth_mint_count,
+                synth_redeem_count = EXCLUDED.synth_redeem_count,
+                total_count = EXCLUDED.total_count,
+                to_asset_volume = EXCLUDED.to_asset_volume,
+                to_rune_volume = EXCLUDED.to_rune_volume,
+                to_trade_volume = EXCLUDED.to_trade_volume,
+                from_trade_volume = EXCLUDED.from_trade_volume,
+                synth_mint_volume = EXCLUDED.synth_mint_volume,
+                synth_redeem_volume = EXCLUDED.synth_redeem_volume,
+                total_volume = EXCLUDED.total_volume,
+                to_asset_volume_usd = EXCLUDED.to_asset_volume_usd,
+                to_rune_volume_usd = EXCLUDED.to_rune_volume_usd,
+                to_trade_volume_usd = EXCLUDED.to_trade_volume_usd,
+                from_trade_volume_usd = EXCLUDED.from_trade_volume_usd,
+                synth_mint_volume_usd = EXCLUDED.synth_mint_volume_usd,
+                synth_redeem_volume_usd = EXCLUDED.synth_redeem_volume_usd,
+                total_volume_usd = EXCLUDED.total_volume_usd,
+                to_asset_fees = EXCLUDED.to_asset_fees,
+                to_rune_fees = EXCLUDED.to_rune_fees,
+                to_trade_fees = EXCLUDED.to_trade_fees,
+                from_trade_fees = EXCLUDED.from_trade_fees,
+                synth_mint_fees = EXCLUDED.synth_mint_fees,
+                synth_redeem_fees = EXCLUDED.synth_redeem_fees,
+                total_fees = EXCLUDED.total_fees,
+                to_asset_average_slip = EXCLUDED.to_asset_average_slip,
+                to_rune_average_slip = EXCLUDED.to_rune_average_slip,
+                to_trade_average_slip = EXCLUDED.to_trade_average_slip,
+                from_trade_average_slip = EXCLUDED.from_trade_average_slip,
+                synth_mint_average_slip = EXCLUDED.synth_mint_average_slip,
+                synth_redeem_average_slip = EXCLUDED.synth_redeem_average_slip,
+                average_slip = EXCLUDED.average_slip,
+                rune_price_usd = EXCLUDED.rune_price_usd,
+                is_final = EXCLUDED.is_final,
+                interval = EXCLUDED.interval",
+    )
+    .bind(column(data, |item| item.start_time))
+    .bind(column(data, |item| item.end_time))
+    .bind(column(data, |item| item.to_asset_count))
+    .bind(column(data, |item| item.to_rune_count))
+    .bind(column(data, |item| item.to_trade_count))
+    .bind(column(data, |item| item.from_trade_count))
+    .bind(column(data, |item| item.synth_mint_count))
+    .bind(column(data, |item| item.synth_redeem_count))
+    .bind(column(data, |item| item.total_count))
+    .bind(column(data, |item| item.to_asset_volume))
+    .bind(column(data, |item| item.to_rune_volume))
+    .bind(column(data, |item| item.to_trade_volume))
+    .bind(column(data, |item| item.from_trade_volume))
+    .bind(column(data, |item| item.synth_mint_volume))
+    .bind(column(data, |item| item.synth_redeem_volume))
+    .bind(column(data, |item| item.total_volume))
+    .bind(column(data, |item| item.to_asset_volume_usd))
+    .bind(column(data, |item| item.to_rune_volume_usd))
+    .bind(column(data, |item| item.to_trade_volume_usd))
+    .bind(column(data, |item| item.from_trade_volume_usd))
+    .bind(column(data, |item| item.synth_mint_volume_usd))
+    .bind(column(data, |item| item.synth_redeem_volume_usd))
+    .bind(column(data, |item| item.total_volume_usd))
+    .bind(column(data, |item| item.to_asset_fees))
+    .bind(column(data, |item| item.to_rune_fees))
+    .bind(column(data, |item| item.to_trade_fees))
+    .bind(column(data, |item| item.from_trade_fees))
+    .bind(column(data, |item| item.synth_mint_fees))
+    .bind(column(data, |item| item.synth_redeem_fees))
+    .bind(column(data, |item| item.total_fees))
+    .bind(column(data, |item| item.to_asset_average_slip.clone()))
+    .bind(column(data, |item| item.to_rune_average_slip.clone()))
+    .bind(column(data, |item| item.to_trade_average_slip.clone()))
+    .bind(column(data, |item| item.from_trade_average_slip.clone()))
+    .bind(column(data, |item| item.synth_mint_average_slip.clone()))
+    .bind(column(data, |item| item.synth_redeem_average_slip.clone()))
+    .bind(column(data, |item| item.average_slip.clone()))
+    .bind(column(data, |item| item.rune_price_usd.clone()))
+    .bind(column(data, |item| item.is_final))
+    .bind(column(data, |item| item.interval.clone()))
+    .execute(&mut *conn)
+    .await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn span(interval: &str) -> Duration {
+        match interval {
+            "day" => Duration::days(1),
+            _ => Duration::hours(1),
+        }
+    }
+
+    fn rune_pool(start_time: DateTime<Utc>, interval: &str, units: i64) -> RunePoolHistory {
+        RunePoolHistory {
+            start_time,
+            end_time: start_time + span(interval),
+            count: 1,
+            units,
+            is_final: true,
+            interval: interval.to_string(),
+        }
+    }
+
+    fn earnings(start_time: DateTime<Utc>, interval: &str, pool: &str) -> EarningsHistory {
+        EarningsHistory {
+            start_time,
+            end_time: start_time + span(interval),
+            liquidity_fees: 0,
+            block_rewards: 0,
+            earnings: 0,
+            bonding_earnings: 0,
+            liquidity_earnings: 0,
+            avg_node_count: 0.into(),
+            rune_price_usd: 0.into(),
+            pools: vec![PoolEarnings {
+                pool: pool.to_string(),
+                asset_liquidity_fees: 0,
+                rune_liquidity_fees: 0,
+                total_liquidity_fees_rune: 0,
+                saver_earning: 0,
+                rewards: 0,
+                earnings: 0,
+            }],
+            is_final: true,
+            interval: interval.to_string(),
+        }
+    }
+
+    async fn stored_rune_pool(pool: &PgPool) -> Vec<(DateTime<Utc>, String, i64)> {
+        sqlx::query_as(
+            "SELECT start_time, interval, units FROM rune_pool_history ORDER BY start_time",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn day_row_replaces_the_hours_it_covers(pool: PgPool) {
+        let midnight = Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap();
+        let next_day = midnight + Duration::days(1);
+        let mut conn = pool.acquire().await.unwrap();
+        let hours: Vec<_> = (0..3)
+            .map(|hour| rune_pool(midnight + Duration::hours(hour), "hour", hour))
+            .chain([rune_pool(next_day, "hour", 24)])
+            .collect();
+        insert_rune_pool_history(&mut conn, &hours).await.unwrap();
+
+        insert_rune_pool_history(&mut conn, &[rune_pool(midnight, "day", 100)])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            stored_rune_pool(&pool).await,
+            vec![
+                (midnight, "day".to_string(), 100),
+                (next_day, "hour".to_string(), 24)
+            ]
+        );
+    }
+
+    #[sqlx::test]
+    async fn hourly_rows_replace_an_overlapping_day_row(pool: PgPool) {
+        let midnight = Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap();
+        let afternoon = midnight + Duration::hours(13);
+        let mut conn = pool.acquire().await.unwrap();
+        insert_rune_pool_history(&mut conn, &[rune_pool(midnight, "day", 100)])
+            .await
+            .unwrap();
+
+        insert_rune_pool_history(&mut conn, &[rune_pool(afternoon, "hour", 13)])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            stored_rune_pool(&pool).await,
+            vec![(afternoon, "hour".to_string(), 13)]
+        );
+    }
+
+    #[sqlx::test]
+    async fn pool_earnings_are_not_merged_across_resolutions(pool: PgPool) {
+        let midnight = Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap();
+        let mut conn = pool.acquire().await.unwrap();
+        insert_earnings_history(&mut conn, &[earnings(midnight, "day", "BTC.BTC")])
+            .await
+            .unwrap();
+
+        insert_earnings_history(&mut conn, &[earnings(midnight, "hour", "ETH.ETH")])
+            .await
+            .unwrap();
+
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT e.interval, p.pool FROM earnings_history e
+             JOIN pool_earnings p ON p.earnings_history_id = e.id",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(rows, vec![("hour".to_string(), "ETH.ETH".to_string())]);
+    }
+}
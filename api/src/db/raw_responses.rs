@@ -0,0 +1,90 @@
+use crate::midgard_api::{Params, RawResponse};
+use crate::models::Resource;
+use chrono::{DateTime, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use sha2::{Digest, Sha256};
+use sqlx::{Executor, FromRow, PgPool, Postgres};
+use std::io::{Read, Write};
+
+#[derive(Debug, FromRow)]
+pub struct ArchivedResponse {
+    pub id: i64,
+    pub resource: String,
+    pub pool: String,
+    pub interval: String,
+    pub fetched_at: DateTime<Utc>,
+    pub body: Vec<u8>,
+}
+
+impl ArchivedResponse {
+    pub fn decompressed_body(&self) -> std::io::Result<String> {
+        let mut body = String::new();
+        GzDecoder::new(self.body.as_slice()).read_to_string(&mut body)?;
+        Ok(body)
+    }
+}
+
+// Stores a history response body gzipped, keyed by the SHA-256 of the body
+pub async fn archive_response<'e>(
+    executor: impl Executor<'e, Database = Postgres>,
+    resource: Resource,
+    pool: Option<&str>,
+    params: &Params,
+    raw: &RawResponse,
+) -> anyhow::Result<()> {
+    let content_hash = hex::encode(Sha256::digest(raw.body.as_bytes()));
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(raw.body.as_bytes())?;
+    let body = encoder.finish()?;
+
+    sqlx::query(
+        "INSERT INTO raw_responses (resource, pool, url, interval, from_time, count, fetched_at, content_hash, body)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+    )
+    .bind(resource.as_str())
+    .bind(pool.unwrap_or(""))
+    .bind(raw.url.as_str())
+    .bind(&params.interval)
+    .bind(params.from)
+    .bind(i32::from(params.count))
+    .bind(raw.fetched_at)
+    .bind(content_hash)
+    .bind(body)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+// Archived responses in fetch order, one page at a time after `after`. A
+// response matches a time range when its requested window overlaps it
+pub async fn get_archived_responses(
+    pool: &PgPool,
+    resource: Option<Resource>,
+    pool_asset: Option<&str>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    after: Option<(DateTime<Utc>, i64)>,
+    limit: i64,
+) -> Result<Vec<ArchivedResponse>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT id, resource, pool, interval, fetched_at, body
+         FROM raw_responses
+         WHERE ($1::TEXT IS NULL OR resource = $1)
+           AND ($2::TEXT IS NULL OR pool = $2)
+           AND ($3::TIMESTAMPTZ IS NULL
+                OR from_time + count * CASE interval WHEN 'day' THEN INTERVAL '1 day' ELSE INTERVAL '1 hour' END > $3)
+           AND ($4::TIMESTAMPTZ IS NULL OR from_time < $4)
+           AND ($5::TIMESTAMPTZ IS NULL OR (fetched_at, id) > ($5, $6))
+         ORDER BY fetched_at, id
+         LIMIT $7",
+    )
+    .bind(resource.map(|resource| resource.as_str()))
+    .bind(pool_asset)
+    .bind(from)
+    .bind(to)
+    .bind(after.map(|(fetched_at, _)| fetched_at))
+    .bind(after.map(|(_, id)| id))
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,149 @@
+use crate::midgard_api::Params;
+use crate::models::{Resource, ResourceFreshness, SyncRun, SyncRunWindow, SyncTrigger};
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool};
+
+const SYNC_RUN_COLUMNS: &str = "id, trigger, started_at, finished_at, status, resources, windows_fetched, rows_upserted, retries, error";
+
+#[derive(Debug, Default)]
+pub struct SyncRunStats {
+    pub resources: Vec<String>,
+    pub windows_fetched: i32,
+    pub rows_upserted: i64,
+    pub retries: i64,
+    pub errors: Vec<String>,
+    // Stopped early by shutdown
+    pub interrupted: bool,
+}
+
+pub async fn start_sync_run(pool: &PgPool, trigger: SyncTrigger) -> Result<SyncRun, sqlx::Error> {
+    sqlx::query_as::<_, SyncRun>(&format!(
+        "INSERT INTO sync_runs (trigger) VALUES ($1) RETURNING {SYNC_RUN_COLUMNS}"
+    ))
+    .bind(trigger.as_str())
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn finish_sync_run(
+    pool: &PgPool,
+    id: i32,
+    stats: &SyncRunStats,
+) -> Result<(), sqlx::Error> {
+    let (status, error) = if !stats.errors.is_empty() {
+        ("failed", Some(stats.errors.join("\n")))
+    } else if stats.interrupted {
+        ("interrupted", None)
+    } else {
+        ("succeeded", None)
+    };
+
+    sqlx::query(
+        "UPDATE sync_runs
+         SET finished_at = now(), status = $2, resources = $3, windows_fetched = $4,
+             rows_upserted = $5, retries = $6, error = $7
+         WHERE id = $1",
+    )
+    .bind(id)
+    .bind(status)
+    .bind(&stats.resources)
+    .bind(stats.windows_fetched)
+    .bind(stats.rows_upserted)
+    .bind(stats.retries)
+    .bind(error)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// Closes runs left `running` by a process that stopped before recording them,
+// at the shutdown deadline or in a crash. Only the leader ingests, so the
+// leader calls it when every such run is its own or a previous leader's
+pub async fn interrupt_running_sync_runs(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE sync_runs SET finished_at = now(), status = 'interrupted'
+         WHERE status = 'running'",
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+// Written in the transaction that stores the window's rows
+pub async fn record_sync_run_window<'e, E>(
+    executor: E,
+    run_id: i32,
+    resource: Resource,
+    pool_asset: Option<&str>,
+    params: &Params,
+    rows_upserted: usize,
+    fetched_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query(
+        "INSERT INTO sync_run_windows (run_id, resource, pool, interval, from_time, count, rows_upserted, fetched_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+    )
+    .bind(run_id)
+    .bind(resource.as_str())
+    .bind(pool_asset.unwrap_or(""))
+    .bind(&params.interval)
+    .bind(params.from)
+    .bind(i32::from(params.count))
+    .bind(i32::try_from(rows_upserted).unwrap_or(i32::MAX))
+    .bind(fetched_at)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+pub async fn list_sync_run_windows(
+    pool: &PgPool,
+    run_id: i32,
+) -> Result<Vec<SyncRunWindow>, sqlx::Error> {
+    sqlx::query_as::<_, SyncRunWindow>(
+        "SELECT resource, NULLIF(pool, '') AS pool, interval, from_time, count, rows_upserted, fetched_at
+         FROM sync_run_windows
+         WHERE run_id = $1
+         ORDER BY resource, pool, from_time",
+    )
+    .bind(run_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn list_sync_runs(pool: &PgPool, limit: i64) -> Result<Vec<SyncRun>, sqlx::Error> {
+    sqlx::query_as::<_, SyncRun>(&format!(
+        "SELECT {SYNC_RUN_COLUMNS} FROM sync_runs ORDER BY started_at DESC LIMIT $1"
+    ))
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn latest_sync_run(
+    pool: &PgPool,
+    status: Option<&str>,
+) -> Result<Option<SyncRun>, sqlx::Error> {
+    sqlx::query_as::<_, SyncRun>(&format!(
+        "SELECT {SYNC_RUN_COLUMNS} FROM sync_runs
+         WHERE $1::TEXT IS NULL OR status = $1
+         ORDER BY started_at DESC LIMIT 1"
+    ))
+    .bind(status)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn resource_freshness(pool: &PgPool) -> Result<Vec<ResourceFreshness>, sqlx::Error> {
+    sqlx::query_as::<_, ResourceFreshness>(
+        "SELECT resource, NULLIF(pool, '') AS pool, last_end_time, updated_at,
+                EXTRACT(EPOCH FROM now() - last_end_time)::BIGINT AS lag_seconds
+         FROM sync_state
+         ORDER BY resource, pool",
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,77 @@
+use crate::models::Resource;
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool};
+
+pub async fn get_cursor(
+    pool: &PgPool,
+    resource: Resource,
+    pool_asset: Option<&str>,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let last_end_time: Option<DateTime<Utc>> = sqlx::query_scalar(
+        "SELECT last_end_time FROM sync_state WHERE resource = $1 AND pool = $2",
+    )
+    .bind(resource.as_str())
+    .bind(pool_asset.unwrap_or(""))
+    .fetch_optional(pool)
+    .await?;
+    Ok(last_end_time)
+}
+
+pub async fn set_cursor<'e, E>(
+    executor: E,
+    resource: Resource,
+    pool_asset: Option<&str>,
+    last_end_time: DateTime<Utc>,
+) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query(
+        "INSERT INTO sync_state (resource, pool, last_end_time, updated_at)
+         VALUES ($1, $2, $3, now())
+         ON CONFLICT (resource, pool) DO UPDATE SET last_end_time = EXCLUDED.last_end_time, updated_at = now()",
+    )
+    .bind(resource.as_str())
+    .bind(pool_asset.unwrap_or(""))
+    .bind(last_end_time)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_backfilled_from(
+    pool: &PgPool,
+    resource: Resource,
+    pool_asset: Option<&str>,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let backfilled_from: Option<Option<DateTime<Utc>>> = sqlx::query_scalar(
+        "SELECT backfilled_from FROM sync_state WHERE resource = $1 AND pool = $2",
+    )
+    .bind(resource.as_str())
+    .bind(pool_asset.unwrap_or(""))
+    .fetch_optional(pool)
+    .await?;
+    Ok(backfilled_from.flatten())
+}
+
+// Only ever moves back, a later backfill never forgets older history it covered
+pub async fn set_backfilled_from<'e, E>(
+    executor: E,
+    resource: Resource,
+    pool_asset: Option<&str>,
+    backfilled_from: DateTime<Utc>,
+) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query(
+        "UPDATE sync_state SET backfilled_from = LEAST(backfilled_from, $3)
+         WHERE resource = $1 AND pool = $2",
+    )
+    .bind(resource.as_str())
+    .bind(pool_asset.unwrap_or(""))
+    .bind(backfilled_from)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use crate::midgard_api::InterfaceError;
+use crate::models::Resource;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+pub async fn record_upstream_error(
+    pool: &PgPool,
+    run_id: i32,
+    run_started_at: DateTime<Utc>,
+    resource: Resource,
+    pool_asset: Option<&str>,
+    error: &anyhow::Error,
+) -> Result<(), sqlx::Error> {
+    // Database failures inside a sync window are not upstream errors
+    let Some(interface_error) = error.downcast_ref::<InterfaceError>() else {
+        return Ok(());
+    };
+
+    sqlx::query(
+        "INSERT INTO upstream_errors (run_id, run_started_at, resource, pool, kind, url, http_status, detail)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+    )
+    .bind(run_id)
+    .bind(run_started_at)
+    .bind(resource.as_str())
+    .bind(pool_asset.unwrap_or(""))
+    .bind(interface_error.kind())
+    .bind(interface_error.url().map(|url| url.to_string()))
+    .bind(interface_error.status().map(|status| i32::from(status.as_u16())))
+    .bind(format!("{error:#}"))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
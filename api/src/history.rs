@@ -0,0 +1,233 @@
+use crate::db::insertions;
+use crate::models::{DepthPriceHistory, EarningsHistory, RunePoolHistory, SwapsHistory};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use sqlx::PgConnection;
+use std::{collections::HashSet, future::Future};
+
+// What every history model shares, whether its intervals were fetched from
+// Midgard, imported from an export or replayed from the archive
+pub trait HistoryRecord: DeserializeOwned + Send + Sync + Sized {
+    // Midgard endpoint, per-pool resources append the pool as a path segment
+    const PATH: &'static str;
+
+    fn start_time(&self) -> DateTime<Utc>;
+    fn end_time(&self) -> DateTime<Utc>;
+    fn is_final(&self) -> bool;
+
+    // Fills in what Midgard leaves out of each interval. Intervals ending after
+    // final_before may not be fully indexed yet and stay provisional
+    fn complete(&mut self, pool: Option<&str>, resolution: &str, final_before: DateTime<Utc>);
+
+    fn insert(
+        conn: &mut PgConnection,
+        data: &[Self],
+    ) -> impl Future<Output = Result<(), sqlx::Error>> + Send;
+}
+
+// Runs `$body` with `$record` naming the model `$resource` is stored as, the
+// one place a resource is mapped to its type
+macro_rules! with_history_record {
+    ($resource:expr, $record:ident => $body:expr) => {
+        match $resource {
+            $crate::models::Resource::Depth => {
+                type $record = $crate::models::DepthPriceHistory;
+                $body
+            }
+            $crate::models::Resource::Earnings => {
+                type $record = $crate::models::EarningsHistory;
+                $body
+            }
+            $crate::models::Resource::RunePool => {
+                type $record = $crate::models::RunePoolHistory;
+                $body
+            }
+            $crate::models::Resource::Swaps => {
+                type $record = $crate::models::SwapsHistory;
+                $body
+            }
+        }
+    };
+}
+pub(crate) use with_history_record;
+
+// What one batch of intervals left in the database
+pub struct StoredIntervals {
+    pub rows: usize,
+    // Earliest start and latest end of the stored intervals
+    pub span: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    pub last_final_end_time: Option<DateTime<Utc>>,
+}
+
+// Completes, dedupes and upserts one response's intervals, dropping those
+// `keep` rejects first
+pub async fn store_intervals<T: HistoryRecord>(
+    conn: &mut PgConnection,
+    mut intervals: Vec<T>,
+    pool: Option<&str>,
+    resolution: &str,
+    final_before: DateTime<Utc>,
+    keep: impl Fn(DateTime<Utc>) -> bool,
+) -> Result<StoredIntervals, sqlx::Error> {
+    for interval in &mut intervals {
+        interval.complete(pool, resolution, final_before);
+    }
+    let mut intervals = dedupe_intervals(intervals, T::start_time);
+    intervals.retain(|interval| keep(interval.start_time()));
+    T::insert(conn, &intervals).await?;
+
+    let from = intervals.iter().map(T::start_time).min();
+    let to = intervals.iter().map(T::end_time).max();
+    Ok(StoredIntervals {
+        rows: intervals.len(),
+        span: from.zip(to),
+        last_final_end_time: intervals
+            .iter()
+            .filter(|interval| interval.is_final())
+            .map(T::end_time)
+            .max(),
+    })
+}
+
+// Exports and archived bodies may repeat an interval, and one upsert cannot
+// touch a row twice. The last copy wins, as a later fetch would
+fn dedupe_intervals<T>(intervals: Vec<T>, key: impl Fn(&T) -> DateTime<Utc>) -> Vec<T> {
+    let mut seen = HashSet::new();
+    let mut deduped: Vec<T> = intervals
+        .into_iter()
+        .rev()
+        .filter(|interval| seen.insert(key(interval)))
+        .collect();
+    deduped.reverse();
+    deduped
+}
+
+impl HistoryRecord for DepthPriceHistory {
+    const PATH: &'static str = "history/depths";
+
+    fn start_time(&self) -> DateTime<Utc> {
+        self.start_time
+    }
+
+    fn end_time(&self) -> DateTime<Utc> {
+        self.end_time
+    }
+
+    fn is_final(&self) -> bool {
+        self.is_final
+    }
+
+    // Midgard does not echo the pool back in each interval
+    fn complete(&mut self, pool: Option<&str>, resolution: &str, final_before: DateTime<Utc>) {
+        self.pool = pool.unwrap_or_default().to_string();
+        self.is_final = self.end_time <= final_before;
+        self.interval = resolution.to_string();
+    }
+
+    fn insert(
+        conn: &mut PgConnection,
+        data: &[Self],
+    ) -> impl Future<Output = Result<(), sqlx::Error>> + Send {
+        insertions::insert_depth_price_history(conn, data)
+    }
+}
+
+impl HistoryRecord for EarningsHistory {
+    const PATH: &'static str = "history/earnings";
+
+    fn start_time(&self) -> DateTime<Utc> {
+        self.start_time
+    }
+
+    fn end_time(&self) -> DateTime<Utc> {
+        self.end_time
+    }
+
+    fn is_final(&self) -> bool {
+        self.is_final
+    }
+
+    fn complete(&mut self, _pool: Option<&str>, resolution: &str, final_before: DateTime<Utc>) {
+        self.is_final = self.end_time <= final_before;
+        self.interval = resolution.to_string();
+    }
+
+    fn insert(
+        conn: &mut PgConnection,
+        data: &[Self],
+    ) -> impl Future<Output = Result<(), sqlx::Error>> + Send {
+        insertions::insert_earnings_history(conn, data)
+    }
+}
+
+impl HistoryRecord for RunePoolHistory {
+    const PATH: &'static str = "history/runepool";
+
+    fn start_time(&self) -> DateTime<Utc> {
+        self.start_time
+    }
+
+    fn end_time(&self) -> DateTime<Utc> {
+        self.end_time
+    }
+
+    fn is_final(&self) -> bool {
+        self.is_final
+    }
+
+    fn complete(&mut self, _pool: Option<&str>, resolution: &str, final_before: DateTime<Utc>) {
+        self.is_final = self.end_time <= final_before;
+        self.interval = resolution.to_string();
+    }
+
+    fn insert(
+        conn: &mut PgConnection,
+        data: &[Self],
+    ) -> impl Future<Output = Result<(), sqlx::Error>> + Send {
+        insertions::insert_rune_pool_history(conn, data)
+    }
+}
+
+impl HistoryRecord for SwapsHistory {
+    const PATH: &'static str = "history/swaps";
+
+    fn start_time(&self) -> DateTime<Utc> {
+        self.start_time
+    }
+
+    fn end_time(&self) -> DateTime<Utc> {
+        self.end_time
+    }
+
+    fn is_final(&self) -> bool {
+        self.is_final
+    }
+
+    fn complete(&mut self, _pool: Option<&str>, resolution: &str, final_before: DateTime<Utc>) {
+        self.is_final = self.end_time <= final_before;
+        self.interval = resolution.to_string();
+    }
+
+    fn insert(
+        conn: &mut PgConnection,
+        data: &[Self],
+    ) -> impl Future<Output = Result<(), sqlx::Error>> + Send {
+        insertions::insert_swaps_history(conn, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn dedupe_keeps_the_last_copy_in_order() {
+        let at = |hour| Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap();
+        let intervals = vec![(at(0), "a"), (at(1), "b"), (at(0), "c"), (at(2), "d")];
+        assert_eq!(
+            dedupe_intervals(intervals, |(start_time, _)| *start_time),
+            vec![(at(1), "b"), (at(0), "c"), (at(2), "d")]
+        );
+    }
+}
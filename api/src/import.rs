@@ -0,0 +1,72 @@
+use crate::db::history_meta::upsert_history_meta;
+use crate::db::raw_responses::archive_response;
+use crate::history::{store_intervals, with_history_record};
+use crate::midgard_api::{self, HistoryResponse, Params, RawResponse};
+use crate::models::{Interval, Resource};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Duration, Utc};
+use reqwest::Url;
+use sqlx::PgPool;
+use std::{fs, path::Path};
+
+// Upserts a Midgard history export shaped like a `{meta, intervals}` response.
+// The file is archived like a fetched response, so a reindex replays it too
+pub async fn import_file(
+    db_pool: &PgPool,
+    path: &Path,
+    resource: Resource,
+    pool: Option<&str>,
+    interval: Interval,
+    finality_grace: Duration,
+) -> Result<usize> {
+    let body =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let url = fs::canonicalize(path)
+        .ok()
+        .and_then(|path| Url::from_file_path(path).ok())
+        .ok_or_else(|| anyhow!("Failed to resolve {}", path.display()))?;
+    let fetched_at = Utc::now();
+    let final_before = fetched_at - finality_grace;
+    let resolution = interval.as_str();
+    let invalid = || format!("{} is not a valid {} export", path.display(), resource);
+
+    if resource.is_per_pool() && pool.is_none() {
+        bail!("{resource} imports need a pool");
+    }
+
+    let mut tx = db_pool.begin().await?;
+    let (stored, meta) = with_history_record!(resource, Record => {
+        let resp: HistoryResponse<Record> =
+            midgard_api::parse_history(&body).with_context(invalid)?;
+        let stored =
+            store_intervals(&mut tx, resp.intervals, pool, resolution, final_before, |_| true)
+                .await?;
+        (stored, resp.meta)
+    });
+
+    // An empty export has nothing to store or replay
+    let Some((from, to)) = stored.span else {
+        return Ok(0);
+    };
+    // The archive row covers the whole span, holes included, so a reindex of
+    // any range inside it replays this file
+    let step = interval.duration().num_seconds();
+    let count = ((to - from).num_seconds() + step - 1) / step;
+    let params = Params {
+        interval: resolution.to_string(),
+        from,
+        count: u16::try_from(count).unwrap_or(u16::MAX),
+    };
+    let raw = RawResponse {
+        url,
+        fetched_at,
+        body,
+    };
+    archive_response(&mut *tx, resource, pool, &params, &raw).await?;
+    if let Some(meta) = &meta {
+        upsert_history_meta(&mut *tx, resource, pool, resolution, meta, fetched_at).await?;
+    }
+    tx.commit().await?;
+
+    Ok(stored.rows)
+}
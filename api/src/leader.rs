@@ -0,0 +1,138 @@
+use crate::db::sync_runs::interrupt_running_sync_runs;
+use crate::state::AppState;
+use sqlx::{Connection, PgConnection};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+use tokio::time::{self, Duration};
+use tokio_util::sync::CancellationToken;
+
+// Only the replica holding the advisory lock runs scheduled and admin-triggered syncs
+pub struct Leadership {
+    is_leader: AtomicBool,
+    // Cancelled as soon as leadership is lost, aborting runs started under it
+    term: Mutex<CancellationToken>,
+}
+
+impl Leadership {
+    pub fn new() -> Self {
+        let term = CancellationToken::new();
+        term.cancel();
+        Self {
+            is_leader: AtomicBool::new(false),
+            term: Mutex::new(term),
+        }
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.is_leader.load(Ordering::Relaxed)
+    }
+
+    // Token of the current term, already cancelled while not the leader
+    pub fn term(&self) -> CancellationToken {
+        self.term.lock().unwrap().clone()
+    }
+
+    fn set(&self, is_leader: bool) {
+        let mut term = self.term.lock().unwrap();
+        if self.is_leader.swap(is_leader, Ordering::Relaxed) != is_leader {
+            if is_leader {
+                *term = CancellationToken::new();
+                println!("Acquired ingestion leadership");
+            } else {
+                term.cancel();
+                eprintln!("Lost ingestion leadership");
+            }
+        }
+    }
+
+    fn release(&self) {
+        let term = self.term.lock().unwrap();
+        self.is_leader.store(false, Ordering::Relaxed);
+        term.cancel();
+    }
+}
+
+// The lock is session-level, so it is released as soon as the holding
+// connection closes and another replica picks it up on its next attempt
+pub async fn start_leader_election(state: AppState) {
+    // Settle the first attempt before the scheduler's startup runs
+    let mut conn: Option<PgConnection> = None;
+    check_leadership(&state, &mut conn).await;
+
+    tokio::spawn(async move {
+        let interval = Duration::from_secs(state.config.leader_check_interval_secs);
+        loop {
+            tokio::select! {
+                _ = time::sleep(interval) => check_leadership(&state, &mut conn).await,
+                _ = state.shutdown.cancelled() => break,
+            }
+        }
+
+        // Keep the lock until in-flight ingestion has stopped, then release it
+        state.tasks.wait().await;
+        if let Some(session) = conn.take() {
+            if state.leadership.is_leader() {
+                let _ = session.close().await;
+                state.leadership.release();
+                println!("Released ingestion leadership");
+            }
+        }
+    });
+}
+
+async fn check_leadership(state: &AppState, conn: &mut Option<PgConnection>) {
+    let held = match conn.as_mut() {
+        // Leader: make sure the session holding the lock is still alive
+        Some(session) if state.leadership.is_leader() => session.ping().await.is_ok(),
+        _ => try_acquire(state, state.config.leader_lock_key, conn).await,
+    };
+
+    if !held && state.leadership.is_leader() {
+        // Drop the session so the lock cannot linger on a broken connection
+        *conn = None;
+    }
+    let acquired = held && !state.leadership.is_leader();
+    state.leadership.set(held);
+
+    // Runs a previous leader never finished would otherwise stay `running`
+    if acquired {
+        match interrupt_running_sync_runs(&state.db_pool).await {
+            Ok(0) => {}
+            Ok(count) => {
+                println!("Marked {count} sync runs left by a previous leader as interrupted")
+            }
+            Err(error) => eprintln!("Failed to close sync runs left running | {error}"),
+        }
+    }
+}
+
+async fn try_acquire(state: &AppState, key: i64, conn: &mut Option<PgConnection>) -> bool {
+    if conn.is_none() {
+        // Detached so the pool never recycles the session that owns the lock
+        match state.db_pool.acquire().await {
+            Ok(pooled) => *conn = Some(pooled.detach()),
+            Err(error) => {
+                eprintln!("Failed to open leader election connection | {error}");
+                return false;
+            }
+        }
+    }
+
+    let Some(session) = conn.as_mut() else {
+        return false;
+    };
+    match sqlx::query_scalar::<_, bool>("SELECT pg_try_advisory_lock($1)")
+        .bind(key)
+        .fetch_one(session)
+        .await
+    {
+        Ok(acquired) => acquired,
+        Err(error) => {
+            eprintln!("Failed to try leader lock | {error}");
+            *conn = None;
+            false
+        }
+    }
+}
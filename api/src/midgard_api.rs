@@ -1,7 +1,8 @@
+pub mod client;
 pub mod handlers;
 pub mod interface;
+pub mod rate_limit;
 
-pub use handlers::{
-    fetch_depth_price_history, fetch_earnings_history, fetch_rune_pool_history, fetch_swaps_history,
-};
-pub use interface::Params;
+pub use client::{finality_grace, MidgardClient, RetryCounter};
+pub use handlers::{fetch_history, fetch_pools};
+pub use interface::{parse_history, HistoryResponse, InterfaceError, Params, RawResponse};
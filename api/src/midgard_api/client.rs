@@ -0,0 +1,136 @@
+use super::rate_limit::RateLimiter;
+use crate::config::Config;
+use rand::Rng;
+use reqwest::{Client, Url};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+use tokio::time::{Duration, Instant};
+
+struct Endpoint {
+    base_url: Url,
+    unhealthy_until: Mutex<Option<Instant>>,
+}
+
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    base_delay: Duration,
+    // Longest wait between attempts, a longer Retry-After fails over instead
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    // Exponential backoff with jitter in [delay / 2, delay]
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let half = delay / 2;
+        half + delay.mul_f64(rand::thread_rng().gen_range(0.0..=0.5))
+    }
+}
+
+// Backoff retries of the requests made on behalf of one sync run, which
+// may overlap with other runs sharing the client
+#[derive(Clone, Default)]
+pub struct RetryCounter(Arc<AtomicU64>);
+
+impl RetryCounter {
+    pub fn record(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+pub struct MidgardClient {
+    pub http: Client,
+    endpoints: Vec<Endpoint>,
+    failover_cooldown: Duration,
+    pub retry: RetryPolicy,
+    pub rate_limiter: RateLimiter,
+    pub finality_grace: chrono::Duration,
+}
+
+impl MidgardClient {
+    pub fn new(config: &Config) -> Result<Self, reqwest::Error> {
+        // One pooled client for every upstream request
+        let http = Client::builder()
+            .user_agent(&config.midgard_user_agent)
+            .connect_timeout(Duration::from_secs(config.midgard_connect_timeout_secs))
+            .read_timeout(Duration::from_secs(config.midgard_read_timeout_secs))
+            .timeout(Duration::from_secs(config.midgard_timeout_secs))
+            .pool_idle_timeout(Duration::from_secs(90))
+            .tcp_keepalive(Duration::from_secs(60))
+            .gzip(true)
+            .brotli(true)
+            .build()?;
+
+        let endpoints = config
+            .midgard_urls
+            .iter()
+            .map(|base_url| Endpoint {
+                base_url: base_url.clone(),
+                unhealthy_until: Mutex::new(None),
+            })
+            .collect();
+
+        Ok(Self {
+            http,
+            endpoints,
+            failover_cooldown: Duration::from_secs(config.midgard_failover_cooldown_secs),
+            retry: RetryPolicy {
+                max_retries: config.midgard_max_retries,
+                base_delay: Duration::from_millis(config.midgard_backoff_base_ms),
+                max_delay: Duration::from_millis(config.midgard_backoff_max_ms),
+            },
+            rate_limiter: RateLimiter::new(
+                config.midgard_rate_limit_per_sec,
+                config.midgard_rate_limit_burst,
+            ),
+            finality_grace: finality_grace(config),
+        })
+    }
+
+    // Healthy endpoints first in configured order, then those still cooling down
+    pub fn endpoint_order(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let (healthy, unhealthy): (Vec<usize>, Vec<usize>) =
+            (0..self.endpoints.len()).partition(|&index| {
+                match *self.endpoints[index].unhealthy_until.lock().unwrap() {
+                    Some(until) => until <= now,
+                    None => true,
+                }
+            });
+
+        healthy.into_iter().chain(unhealthy).collect()
+    }
+
+    pub fn base_url(&self, index: usize) -> &Url {
+        &self.endpoints[index].base_url
+    }
+
+    pub fn mark_healthy(&self, index: usize) {
+        *self.endpoints[index].unhealthy_until.lock().unwrap() = None;
+    }
+
+    pub fn mark_unhealthy(&self, index: usize) {
+        self.mark_unhealthy_for(index, self.failover_cooldown);
+    }
+
+    // Keeps an endpoint at the back of the order for at least `cooldown`
+    pub fn mark_unhealthy_for(&self, index: usize, cooldown: Duration) {
+        *self.endpoints[index].unhealthy_until.lock().unwrap() =
+            Some(Instant::now() + cooldown.max(self.failover_cooldown));
+    }
+}
+
+// Midgard indexes a few blocks behind the chain, so an interval only counts as
+// final once it closed at least this long before the fetch
+pub fn finality_grace(config: &Config) -> chrono::Duration {
+    chrono::Duration::seconds(i64::from(config.midgard_finality_grace_secs))
+}
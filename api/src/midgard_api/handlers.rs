@@ -1,27 +1,31 @@
-use super::{interface::Interface, Params};
-use crate::models::{DepthPriceHistory, EarningsHistory, RunePoolHistory, SwapsHistory};
+use super::{
+    client::{MidgardClient, RetryCounter},
+    interface::{HistoryPage, Interface},
+    Params,
+};
+use crate::history::HistoryRecord;
+use crate::models::Pool;
 use anyhow::Result;
+use std::sync::Arc;
 
-pub async fn fetch_depth_price_history(params: Params) -> Result<Vec<DepthPriceHistory>> {
-    let api_interface = Interface::new("depths/BTC.BTC".to_string(), params);
-    println!("Fetching depth price history...");
-    api_interface.fetch_data().await
+// Fetches one page of history. Per-pool resources pass their pool, which is
+// escaped into the path
+pub async fn fetch_history<T: HistoryRecord>(
+    client: &Arc<MidgardClient>,
+    pool: Option<&str>,
+    params: Params,
+    retries: &RetryCounter,
+) -> Result<HistoryPage<T>> {
+    let mut api_interface = Interface::new(Arc::clone(client), T::PATH.to_string(), params);
+    if let Some(pool) = pool {
+        api_interface = api_interface.with_segment(pool);
+    }
+    println!("Fetching {}...", T::PATH);
+    api_interface.with_retries(retries).fetch_data().await
 }
 
-pub async fn fetch_earnings_history(params: Params) -> Result<Vec<EarningsHistory>> {
-    let api_interface = Interface::new("earnings".to_string(), params);
-    println!("Fetching earnings history...");
-    api_interface.fetch_data().await
-}
-
-pub async fn fetch_rune_pool_history(params: Params) -> Result<Vec<RunePoolHistory>> {
-    let api_interface = Interface::new("runepool".to_string(), params);
-    println!("Fetching rune pool history...");
-    api_interface.fetch_data().await
-}
-
-pub async fn fetch_swaps_history(params: Params) -> Result<Vec<SwapsHistory>> {
-    let api_interface = Interface::new("swaps".to_string(), params);
-    println!("Fetching swaps history...");
-    api_interface.fetch_data().await
+pub async fn fetch_pools(client: &Arc<MidgardClient>) -> Result<Vec<Pool>> {
+    let api_interface = Interface::without_params(Arc::clone(client), "pools".to_string());
+    println!("Fetching pools...");
+    api_interface.fetch_json().await
 }
@@ -1,26 +1,131 @@
+use super::client::{MidgardClient, RetryCounter};
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use reqwest::Url;
-use serde::de::DeserializeOwned;
+use reqwest::{header::RETRY_AFTER, Response, StatusCode, Url};
+use serde::{de::DeserializeOwned, Deserialize};
 use serde_json::Value;
+use std::sync::Arc;
 use thiserror::Error;
+use tokio::time::{self, Duration};
+
+const BODY_SNIPPET_LEN: usize = 512;
 
 #[derive(Error, Debug)]
 pub enum InterfaceError {
-    #[error("Failed to build URL")]
-    UrlConstruction,
+    #[error("Failed to build URL for {resource}")]
+    UrlConstruction { resource: String },
+
+    #[error("Network request to {url} failed")]
+    NetworkRequest {
+        url: Url,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("Request to {url} timed out")]
+    Timeout { url: Url },
+
+    #[error("{url} rate limited the request")]
+    RateLimited {
+        url: Url,
+        retry_after: Option<Duration>,
+    },
+
+    #[error("{url} returned HTTP {status}: {body}")]
+    HttpStatus {
+        url: Url,
+        status: StatusCode,
+        body: String,
+        retry_after: Option<Duration>,
+    },
+
+    #[error("All upstream endpoints failed")]
+    AllUpstreamsFailed,
+
+    #[error("Failed to deserialize {url} at `{path}`: {message} | body: {body}")]
+    Deserialization {
+        url: Url,
+        path: String,
+        message: String,
+        body: String,
+    },
+}
+
+impl InterfaceError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::NetworkRequest { .. } | Self::Timeout { .. } | Self::RateLimited { .. } => true,
+            Self::HttpStatus { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+
+    // Delay the upstream asked for before trying again
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited { retry_after, .. } | Self::HttpStatus { retry_after, .. } => {
+                *retry_after
+            }
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::UrlConstruction { .. } => "url_construction",
+            Self::NetworkRequest { .. } => "network_request",
+            Self::Timeout { .. } => "timeout",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::HttpStatus { .. } => "http_status",
+            Self::AllUpstreamsFailed => "all_upstreams_failed",
+            Self::Deserialization { .. } => "deserialization",
+        }
+    }
+
+    pub fn url(&self) -> Option<&Url> {
+        match self {
+            Self::NetworkRequest { url, .. }
+            | Self::Timeout { url }
+            | Self::RateLimited { url, .. }
+            | Self::HttpStatus { url, .. }
+            | Self::Deserialization { url, .. } => Some(url),
+            _ => None,
+        }
+    }
 
-    #[error("Network request failed")]
-    NetworkRequest,
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::RateLimited { .. } => Some(StatusCode::TOO_MANY_REQUESTS),
+            Self::HttpStatus { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct HistoryResponse<T> {
+    pub intervals: Vec<T>,
+    // Midgard's summary of the whole range, its fields differ per endpoint
+    #[serde(default)]
+    pub meta: Option<Value>,
+}
 
-    #[error("Failed to deserialize data")]
-    Deserialization,
+// A response body exactly as the upstream returned it
+#[derive(Clone, Debug)]
+pub struct RawResponse {
+    pub url: Url,
+    pub fetched_at: DateTime<Utc>,
+    pub body: String,
+}
 
-    #[error("Missing or invalid data in response")]
-    InvalidResponse,
+pub struct HistoryPage<T> {
+    pub intervals: Vec<T>,
+    pub meta: Option<Value>,
+    pub params: Params,
+    pub raw: RawResponse,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Params {
     pub interval: String,
     pub from: DateTime<Utc>,
@@ -28,55 +133,267 @@ pub struct Params {
 }
 
 pub struct Interface {
+    pub client: Arc<MidgardClient>,
     pub resource: String,
-    pub params: Params,
+    // Path segments after the resource, such as a pool, escaped when the URL is built
+    pub segments: Vec<String>,
+    pub params: Option<Params>,
+    pub retries: Option<RetryCounter>,
 }
 
 impl Interface {
-    pub fn new(resource: String, params: Params) -> Self {
-        Self { resource, params }
+    pub fn new(client: Arc<MidgardClient>, resource: String, params: Params) -> Self {
+        Self {
+            client,
+            resource,
+            segments: Vec::new(),
+            params: Some(params),
+            retries: None,
+        }
+    }
+
+    pub fn without_params(client: Arc<MidgardClient>, resource: String) -> Self {
+        Self {
+            client,
+            resource,
+            segments: Vec::new(),
+            params: None,
+            retries: None,
+        }
+    }
+
+    pub fn with_segment(mut self, segment: &str) -> Self {
+        self.segments.push(segment.to_string());
+        self
+    }
+
+    // Counts this request's retries towards a sync run
+    pub fn with_retries(mut self, retries: &RetryCounter) -> Self {
+        self.retries = Some(retries.clone());
+        self
+    }
+
+    pub async fn fetch_json<T>(&self) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let (resp, _) = self.fetch_raw().await?;
+        Ok(resp)
+    }
+
+    async fn fetch_raw<T>(&self) -> Result<(T, RawResponse)>
+    where
+        T: DeserializeOwned,
+    {
+        let mut attempt = 0;
+
+        loop {
+            let mut last_error = None;
+            let mut retryable = false;
+            let mut retry_after = Duration::ZERO;
+
+            // Try each upstream in health order until one answers
+            for index in self.client.endpoint_order() {
+                let base_url = self.client.base_url(index);
+                let url = self.build_url(base_url)?;
+                println!("Upstream: {} | Full URL: {}", base_url, url);
+
+                match self.fetch_from(url).await {
+                    Ok(resp) => {
+                        self.client.mark_healthy(index);
+                        return Ok(resp);
+                    }
+                    Err(error) => {
+                        tracing::warn!(
+                            upstream = %base_url,
+                            resource = %self.resource,
+                            kind = error.kind(),
+                            status = error.status().map(|status| status.as_u16()),
+                            error = %error,
+                            "Upstream request failed, trying next"
+                        );
+                        match error.retry_after() {
+                            // Too long to wait for, leave this upstream alone and fail over
+                            Some(delay) if delay > self.client.retry.max_delay => {
+                                self.client.mark_unhealthy_for(index, delay);
+                            }
+                            requested => {
+                                self.client.mark_unhealthy(index);
+                                retryable |= error.is_retryable();
+                                retry_after = retry_after.max(requested.unwrap_or_default());
+                            }
+                        }
+                        last_error = Some(error);
+                    }
+                }
+            }
+
+            let error = last_error.ok_or(InterfaceError::AllUpstreamsFailed)?;
+            if !retryable || attempt >= self.client.retry.max_retries {
+                return Err(anyhow::Error::new(error).context("All upstream endpoints failed"));
+            }
+
+            // Back off before the next pass, honouring a Retry-After within max_delay
+            let delay = self.client.retry.backoff(attempt).max(retry_after);
+            attempt += 1;
+            if let Some(retries) = &self.retries {
+                retries.record();
+            }
+            eprintln!(
+                "Retrying {} in {:?} (attempt {}/{})",
+                self.resource, delay, attempt, self.client.retry.max_retries
+            );
+            time::sleep(delay).await;
+        }
     }
 
-    pub async fn fetch_data<T>(&self) -> Result<Vec<T>>
+    async fn fetch_from<T>(&self, url: Url) -> Result<(T, RawResponse), InterfaceError>
     where
         T: DeserializeOwned,
     {
-        // Build URL
-        let url = self.build_url()?;
-        println!("Full URL: {}", url);
+        self.client.rate_limiter.acquire().await;
 
         // Send request to Midgard API
-        let resp = reqwest::get(url.clone())
+        let resp = self
+            .client
+            .http
+            .get(url.clone())
+            .send()
             .await
-            .map_err(|_| InterfaceError::NetworkRequest)?
-            .json::<Value>()
-            .await
-            .map_err(|_| InterfaceError::NetworkRequest)
-            .context("Failed to parse response JSON")?;
+            .map_err(|e| request_error(&url, e))?;
+        let fetched_at = Utc::now();
+
+        let status = resp.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(InterfaceError::RateLimited {
+                retry_after: retry_after(&resp),
+                url,
+            });
+        }
+        if !status.is_success() {
+            // 503s in particular may say when the upstream expects to be back
+            let retry_after = retry_after(&resp);
+            let body = resp.text().await.unwrap_or_default();
+            return Err(InterfaceError::HttpStatus {
+                url,
+                status,
+                body: snippet(&body),
+                retry_after,
+            });
+        }
 
-        // Extract & deserialize "intervals" field
-        let intervals_value = resp
-            .get("intervals")
-            .ok_or(InterfaceError::InvalidResponse)
-            .context("No 'intervals' key found in response")?;
+        let body = resp.text().await.map_err(|e| request_error(&url, e))?;
 
-        let intervals: Vec<T> = serde_json::from_value(intervals_value.clone())
-            .map_err(|_| InterfaceError::Deserialization)?;
+        let resp = match parse_json(&body) {
+            Ok(resp) => resp,
+            Err(e) => {
+                return Err(InterfaceError::Deserialization {
+                    path: e.path().to_string(),
+                    message: e.into_inner().to_string(),
+                    body: snippet(&body),
+                    url,
+                })
+            }
+        };
 
-        Ok(intervals)
+        Ok((
+            resp,
+            RawResponse {
+                url,
+                fetched_at,
+                body,
+            },
+        ))
     }
 
-    fn build_url(&self) -> Result<Url> {
-        let base_url = "https://midgard.ninerealms.com/v2/history";
+    pub async fn fetch_data<T>(&self) -> Result<HistoryPage<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let params = self
+            .params
+            .clone()
+            .context("History requests need interval params")?;
+        let (resp, raw): (HistoryResponse<T>, _) = self.fetch_raw().await?;
+        Ok(HistoryPage {
+            intervals: resp.intervals,
+            meta: resp.meta,
+            params,
+            raw,
+        })
+    }
 
-        let mut url = Url::parse(&format!("{}/{}", base_url, self.resource))
-            .map_err(|_| InterfaceError::UrlConstruction)?;
+    fn build_url(&self, base_url: &Url) -> Result<Url> {
+        let mut url = Url::parse(&format!(
+            "{}/{}",
+            base_url.as_str().trim_end_matches('/'),
+            self.resource
+        ))
+        .map_err(|_| InterfaceError::UrlConstruction {
+            resource: self.resource.clone(),
+        })
+        .context("Invalid upstream base URL")?;
+        url.path_segments_mut()
+            .map_err(|_| InterfaceError::UrlConstruction {
+                resource: self.resource.clone(),
+            })?
+            .extend(&self.segments);
 
-        url.query_pairs_mut()
-            .append_pair("interval", &self.params.interval)
-            .append_pair("from", &self.params.from.timestamp().to_string())
-            .append_pair("count", &self.params.count.to_string());
+        if let Some(params) = &self.params {
+            url.query_pairs_mut()
+                .append_pair("interval", &params.interval)
+                .append_pair("from", &params.from.timestamp().to_string())
+                .append_pair("count", &params.count.to_string());
+        }
 
         Ok(url)
     }
 }
+
+// Keeps the path of the offending field when the payload does not match
+pub fn parse_json<T>(body: &str) -> Result<T, serde_path_to_error::Error<serde_json::Error>>
+where
+    T: DeserializeOwned,
+{
+    let deserializer = &mut serde_json::Deserializer::from_str(body);
+    serde_path_to_error::deserialize(deserializer)
+}
+
+// A history response body, such as an archived one or an export
+pub fn parse_history<T>(
+    body: &str,
+) -> Result<HistoryResponse<T>, serde_path_to_error::Error<serde_json::Error>>
+where
+    T: DeserializeOwned,
+{
+    parse_json(body)
+}
+
+fn retry_after(resp: &Response) -> Option<Duration> {
+    let value = resp.headers().get(RETRY_AFTER)?.to_str().ok()?;
+
+    // Either delay-seconds or an HTTP date
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+fn request_error(url: &Url, error: reqwest::Error) -> InterfaceError {
+    if error.is_timeout() {
+        InterfaceError::Timeout { url: url.clone() }
+    } else {
+        InterfaceError::NetworkRequest {
+            url: url.clone(),
+            source: error,
+        }
+    }
+}
+
+fn snippet(body: &str) -> String {
+    match body.char_indices().nth(BODY_SNIPPET_LEN) {
+        Some((index, _)) => format!("{}...", &body[..index]),
+        None => body.to_string(),
+    }
+}
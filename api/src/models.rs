@@ -1,11 +1,28 @@
 mod depth_price_history;
 mod earnings_history;
+mod gap;
+mod history_meta;
+mod interval;
+mod pool;
+mod resource;
 mod rune_pool_history;
 mod swaps_history;
+mod sync_run;
 
 pub use depth_price_history::DepthPriceHistory;
 pub use earnings_history::ApiEarningsHistory;
 pub use earnings_history::EarningsHistory;
 pub use earnings_history::PoolEarnings;
+pub use gap::Gap;
+pub use history_meta::HistoryMeta;
+pub use interval::Interval;
+pub use pool::ApiPool;
+pub use pool::Pool;
+pub use resource::Resource;
 pub use rune_pool_history::RunePoolHistory;
 pub use swaps_history::SwapsHistory;
+pub use sync_run::ResourceFreshness;
+pub use sync_run::SyncRun;
+pub use sync_run::SyncRunWindow;
+pub use sync_run::SyncStatus;
+pub use sync_run::SyncTrigger;
@@ -7,6 +7,9 @@ use sqlx::{types::BigDecimal, FromRow};
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 #[serde(rename_all = "camelCase")]
 pub struct DepthPriceHistory {
+    #[serde(default)]
+    pub pool: String,
+
     #[serde_as(as = "TimestampSeconds<String>")]
     pub start_time: DateTime<Utc>,
 
@@ -43,4 +46,12 @@ pub struct DepthPriceHistory {
 
     #[serde_as(as = "DisplayFromStr")]
     pub luvi: BigDecimal,
+
+    // False while the interval is still open upstream and may change
+    #[serde(default)]
+    pub is_final: bool,
+
+    // Midgard resolution the row was fetched at, `hour` or `day`
+    #[serde(default)]
+    pub interval: String,
 }
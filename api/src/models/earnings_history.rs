@@ -37,6 +37,14 @@ pub struct EarningsHistory {
     pub rune_price_usd: BigDecimal,
 
     pub pools: Vec<PoolEarnings>,
+
+    // False while the interval is still open upstream and may change
+    #[serde(default)]
+    pub is_final: bool,
+
+    // Midgard resolution the row was fetched at, `hour` or `day`
+    #[serde(default)]
+    pub interval: String,
 }
 
 #[serde_as]
@@ -97,4 +105,8 @@ pub struct ApiEarningsHistory {
     pub rune_price_usd: BigDecimal,
 
     pub pools: Vec<Value>,
+
+    pub is_final: bool,
+
+    pub interval: String,
 }
@@ -0,0 +1,15 @@
+use super::Resource;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Gap {
+    pub resource: Resource,
+    pub pool: Option<String>,
+    // Resolution of the row before the gap, which the gap is filled at
+    pub interval: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub missing_intervals: i64,
+}
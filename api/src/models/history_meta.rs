@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{types::Json, FromRow};
+
+// Midgard's range summary for one fetch window, as returned alongside its intervals
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryMeta {
+    pub pool: Option<String>,
+    pub interval: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub fetched_at: DateTime<Utc>,
+    pub meta: Json<Value>,
+}
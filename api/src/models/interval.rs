@@ -0,0 +1,55 @@
+use chrono::{DateTime, Duration, Timelike, Utc};
+use std::{fmt, str::FromStr};
+
+// Midgard resolutions the history tables are ingested at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interval {
+    Hour,
+    Day,
+}
+
+impl Interval {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Interval::Hour => "hour",
+            Interval::Day => "day",
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            Interval::Hour => Duration::hours(1),
+            Interval::Day => Duration::days(1),
+        }
+    }
+
+    // Start of the interval containing `time`
+    pub fn truncate(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let hour = match self {
+            Interval::Hour => time.hour(),
+            Interval::Day => 0,
+        };
+        time.date_naive()
+            .and_hms_opt(hour, 0, 0)
+            .expect("Failed to truncate time")
+            .and_utc()
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "hour" => Ok(Interval::Hour),
+            "day" => Ok(Interval::Day),
+            _ => Err(format!("Unsupported interval: {}", value)),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Resource {
+    #[serde(rename = "depth_price_history")]
+    Depth,
+    #[serde(rename = "earnings_history")]
+    Earnings,
+    #[serde(rename = "rune_pool_history")]
+    RunePool,
+    #[serde(rename = "swaps_history")]
+    Swaps,
+}
+
+impl Resource {
+    pub const ALL: [Resource; 4] = [
+        Resource::Depth,
+        Resource::Earnings,
+        Resource::RunePool,
+        Resource::Swaps,
+    ];
+
+    // Also the name of the table the resource is stored in
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Resource::Depth => "depth_price_history",
+            Resource::Earnings => "earnings_history",
+            Resource::RunePool => "rune_pool_history",
+            Resource::Swaps => "swaps_history",
+        }
+    }
+
+    pub fn is_per_pool(&self) -> bool {
+        matches!(self, Resource::Depth)
+    }
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Resource {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "depth" | "depth_price_history" => Ok(Resource::Depth),
+            "earnings" | "earnings_history" => Ok(Resource::Earnings),
+            "rune-pool" | "rune_pool_history" => Ok(Resource::RunePool),
+            "swaps" | "swaps_history" => Ok(Resource::Swaps),
+            _ => Err(format!("Unknown resource: {}", value)),
+        }
+    }
+}
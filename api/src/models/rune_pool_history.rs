@@ -18,4 +18,12 @@ pub struct RunePoolHistory {
 
     #[serde_as(as = "DisplayFromStr")]
     pub units: i64,
+
+    // False while the interval is still open upstream and may change
+    #[serde(default)]
+    pub is_final: bool,
+
+    // Midgard resolution the row was fetched at, `hour` or `day`
+    #[serde(default)]
+    pub interval: String,
 }
@@ -128,4 +128,12 @@ pub struct SwapsHistory {
     #[serde_as(as = "DisplayFromStr")]
     #[serde(rename = "runePriceUSD")]
     pub rune_price_usd: BigDecimal,
+
+    // False while the interval is still open upstream and may change
+    #[serde(default)]
+    pub is_final: bool,
+
+    // Midgard resolution the row was fetched at, `hour` or `day`
+    #[serde(default)]
+    pub interval: String,
 }
@@ -0,0 +1,76 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncTrigger {
+    Scheduler,
+    Manual,
+    Backfill,
+}
+
+impl SyncTrigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncTrigger::Scheduler => "scheduler",
+            SyncTrigger::Manual => "manual",
+            SyncTrigger::Backfill => "backfill",
+        }
+    }
+}
+
+impl fmt::Display for SyncTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncRun {
+    pub id: i32,
+    pub trigger: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub status: String,
+    pub resources: Vec<String>,
+    pub windows_fetched: i32,
+    pub rows_upserted: i64,
+    pub retries: i64,
+    pub error: Option<String>,
+}
+
+// One Midgard request of a sync run, covering `count` intervals from `from_time`
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncRunWindow {
+    pub resource: String,
+    pub pool: Option<String>,
+    pub interval: String,
+    pub from_time: DateTime<Utc>,
+    pub count: i32,
+    pub rows_upserted: i32,
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceFreshness {
+    pub resource: String,
+    pub pool: Option<String>,
+    pub last_end_time: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    // Seconds between the last stored closed interval and now
+    pub lag_seconds: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    // Whether the replica answering runs ingestion
+    pub is_leader: bool,
+    pub last_run: Option<SyncRun>,
+    pub last_successful_run: Option<SyncRun>,
+    pub resources: Vec<ResourceFreshness>,
+}
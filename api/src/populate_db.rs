@@ -1,111 +1,901 @@
-use crate::db::insertions::{self, get_last_end_time};
-use crate::midgard_api::{self, handlers};
+use crate::config::{Config, DEFAULT_POOL};
+use crate::db::backfill_windows::{
+    clear_backfill_windows, complete_backfill_window, get_backfill_windows, plan_backfill_windows,
+};
+use crate::db::gaps::{find_gaps, get_first_start_time};
+use crate::db::history_meta::upsert_history_meta;
+use crate::db::insertions::{self, get_available_pools};
+use crate::db::raw_responses::archive_response;
+use crate::db::sync_runs::{finish_sync_run, record_sync_run_window, start_sync_run, SyncRunStats};
+use crate::db::sync_state::{get_backfilled_from, get_cursor, set_backfilled_from, set_cursor};
+use crate::db::upstream_errors::record_upstream_error;
+use crate::history::{store_intervals, with_history_record, HistoryRecord};
+use crate::midgard_api::{self, RetryCounter};
+use crate::models::{Interval, Resource, SyncTrigger};
+use crate::state::AppState;
 use crate::utils::get_truncated_now;
+use anyhow::Result;
 use chrono::{DateTime, Duration, Utc};
+use futures::stream::{self, StreamExt};
 use sqlx::PgPool;
+use std::{future::Future, sync::Mutex};
+use tokio_util::sync::CancellationToken;
 
-fn midgard_params(start_time: DateTime<Utc>) -> midgard_api::Params {
+// A range covered by a single Midgard request
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Window {
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    interval: Interval,
+}
+
+// What a written window records alongside its rows, in the same transaction
+#[derive(Clone, Copy)]
+enum WindowProgress {
+    Cursor,
+    BackfillWindow,
+    Untracked,
+}
+
+// The sync run in progress, shared by every window it fetches
+struct SyncRunContext {
+    id: i32,
+    // Leadership term the run was started under
+    leadership: CancellationToken,
+    started_at: DateTime<Utc>,
+    retries: RetryCounter,
+    stats: Mutex<SyncRunStats>,
+}
+
+impl SyncRunContext {
+    async fn start(state: &AppState, trigger: SyncTrigger) -> Option<Self> {
+        if state.shutdown.is_cancelled() {
+            println!("Skipping {trigger} sync run, shutting down");
+            return None;
+        }
+        // Also catches a queued backfill whose replica lost leadership while it waited
+        let leadership = state.leadership.term();
+        if leadership.is_cancelled() {
+            println!("Skipping {trigger} sync run, this replica is not the ingestion leader");
+            return None;
+        }
+
+        match start_sync_run(&state.db_pool, trigger).await {
+            Ok(run) => Some(Self {
+                id: run.id,
+                leadership,
+                started_at: run.started_at,
+                retries: RetryCounter::default(),
+                stats: Mutex::new(SyncRunStats::default()),
+            }),
+            Err(error) => {
+                eprintln!("Failed to start {trigger} sync run | {error}");
+                None
+            }
+        }
+    }
+
+    // True when the run finished without errors or an interruption
+    async fn finish(self, state: &AppState) -> bool {
+        let mut stats = self.stats.into_inner().unwrap();
+        stats.retries = self.retries.get() as i64;
+        if let Err(error) = finish_sync_run(&state.db_pool, self.id, &stats).await {
+            eprintln!("Failed to record sync run {} | {error}", self.id);
+        }
+        stats.errors.is_empty() && !stats.interrupted
+    }
+
+    // Checked before each window: on shutdown the run stops at a window boundary
+    fn should_stop(&self, state: &AppState) -> bool {
+        state.shutdown.is_cancelled() || self.leadership.is_cancelled()
+    }
+
+    // Another replica may already be writing once leadership is lost, so the
+    // window in flight is dropped and its transaction rolled back. None if aborted
+    async fn while_leader<T>(&self, window: impl Future<Output = T>) -> Option<T> {
+        tokio::select! {
+            result = window => Some(result),
+            _ = self.leadership.cancelled() => {
+                self.mark_interrupted();
+                None
+            }
+        }
+    }
+
+    fn add_resource(&self, label: &str) {
+        let mut stats = self.stats.lock().unwrap();
+        if !stats.resources.iter().any(|resource| resource == label) {
+            stats.resources.push(label.to_string());
+        }
+    }
+
+    fn add_window(&self, rows: usize) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.windows_fetched += 1;
+        stats.rows_upserted += rows as i64;
+    }
+
+    fn add_error(&self, message: String) {
+        self.stats.lock().unwrap().errors.push(message);
+    }
+
+    fn mark_interrupted(&self) {
+        self.stats.lock().unwrap().interrupted = true;
+    }
+}
+
+fn midgard_params(
+    interval: Interval,
+    start_time: DateTime<Utc>,
+    count: u16,
+) -> midgard_api::Params {
     midgard_api::Params {
-        interval: "hour".to_string(),
+        interval: interval.to_string(),
         from: start_time,
-        count: 400,
+        count,
     }
 }
 
-pub async fn populate_db(db_pool: &PgPool) {
-    println!("\nPopulating database...");
+pub async fn populate_pools(state: &AppState) {
+    println!("\nSyncing pool catalog...");
+    let db_pool = &*state.db_pool;
+    let midgard_client = &state.midgard_client;
 
-    // Calculate last timestamp in database
-    let default_start_time: DateTime<Utc> = get_truncated_now() - Duration::days(90);
-    let mut last_end_time = match get_last_end_time(db_pool, "depth_price_history").await {
-        Ok(time) => time.unwrap_or(default_start_time),
+    let pools = match midgard_api::fetch_pools(midgard_client).await {
+        Ok(data) => data,
         Err(error) => {
-            eprintln!("Failed to get last end time | {error}");
-            default_start_time
+            eprintln!("Failed to fetch pools | {error}");
+            return;
         }
     };
-    let mut current_iteration: u8 = 1;
+    let result = async {
+        let mut tx = db_pool.begin().await?;
+        insertions::upsert_pools(&mut tx, &pools, Utc::now()).await?;
+        tx.commit().await
+    };
+    if let Err(error) = result.await {
+        eprintln!("Failed to upsert pools: {error}");
+    }
+}
 
-    // Perform fetch and insert operations until last_end_time is within the last hour
-    while last_end_time <= get_truncated_now() - Duration::hours(1) {
-        let params = midgard_params(last_end_time);
+async fn ingestion_pools(db_pool: &PgPool, config: &Config) -> Vec<String> {
+    if let Some(pools) = &config.pools {
+        return pools.clone();
+    }
 
-        println!(
-            "\n\n------------Iteration: {} | From: {}------------\n",
-            current_iteration, &params.from
-        );
+    match get_available_pools(db_pool).await {
+        Ok(pools) if !pools.is_empty() => pools,
+        Ok(_) => vec![DEFAULT_POOL.to_string()],
+        Err(error) => {
+            eprintln!("Failed to get available pools | {error}");
+            vec![DEFAULT_POOL.to_string()]
+        }
+    }
+}
+
+pub async fn populate_db(state: &AppState, resources: &[Resource], trigger: SyncTrigger) -> bool {
+    println!("\nPopulating database...");
+    let Some(run) = SyncRunContext::start(state, trigger).await else {
+        return false;
+    };
+    let pools = ingestion_pools(&state.db_pool, &state.config).await;
+
+    // Each resource (and pool) resumes from its own cursor, so they sync
+    // side by side while the windows within one stay in order
+    let jobs: Vec<(Resource, Option<&str>)> = resources
+        .iter()
+        .flat_map(|&resource| match resource.is_per_pool() {
+            true => pools
+                .iter()
+                .map(|pool| (resource, Some(pool.as_str())))
+                .collect(),
+            false => vec![(resource, None)],
+        })
+        .collect();
+    stream::iter(jobs)
+        .for_each_concurrent(state.config.sync_concurrency, |(resource, pool)| {
+            sync_resource(state, &run, resource, pool)
+        })
+        .await;
+
+    backfill_gaps(state, &run, resources).await;
+    run.finish(state).await
+}
 
-        // Fetch and insert depth price history
-        let depth_price_history = match handlers::fetch_depth_price_history(params.clone()).await {
-            Ok(data) => data,
+// Bounds a requested backfill to history that exists and has closed: `from`
+// rounded down to the hour and no earlier than genesis, `to` at most the current hour
+pub fn backfill_bounds(
+    config: &Config,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<(DateTime<Utc>, DateTime<Utc>), String> {
+    let now = get_truncated_now();
+    let from = Interval::Hour.truncate(from).max(config.midgard_genesis);
+    let to = to.min(now);
+    if from >= now {
+        return Err("The range is entirely in the future".to_string());
+    }
+    if from >= to {
+        return Err("`from` must be before `to`".to_string());
+    }
+    Ok((from, to))
+}
+
+// Re-fetches and upserts an explicit range without touching the cursors
+pub async fn backfill_range(
+    state: &AppState,
+    resource: Resource,
+    pool: Option<&str>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> bool {
+    let label = resource_label(resource, pool);
+    println!("\nBackfilling {label} from {from} to {to}...");
+    let Some(run) = SyncRunContext::start(state, SyncTrigger::Backfill).await else {
+        return false;
+    };
+
+    run.add_resource(&label);
+    let max_count = state.config.midgard_max_count;
+    let windows = split_windows(max_count, from, to, Interval::Hour);
+    let result = sync_windows(
+        state,
+        &run,
+        resource,
+        pool,
+        windows,
+        WindowProgress::Untracked,
+    );
+    if let Err(error) = result.await {
+        eprintln!("Failed to backfill {label} | {error:#}");
+        record_error(state, &run, resource, pool, &error).await;
+    }
+    run.finish(state).await
+}
+
+// Re-fetches holes in the history series without touching the cursors
+async fn backfill_gaps(state: &AppState, run: &SyncRunContext, resources: &[Resource]) {
+    let mut gaps = Vec::new();
+    for &resource in resources {
+        match find_gaps(&state.db_pool, resource).await {
+            Ok(resource_gaps) => gaps.extend(resource_gaps),
             Err(error) => {
-                eprintln!("Failed to fetch depth price history | {error}");
-                return;
+                eprintln!("Failed to find gaps for {resource} | {error}");
+                run.add_error(format!("Failed to find gaps for {resource}: {error}"));
             }
-        };
-        match insertions::insert_depth_price_history(db_pool, &depth_price_history).await {
-            Ok(data) => data,
-            Err(error) => {
-                eprintln!("Failed to insert depth price history: {error}");
-                return;
+        }
+    }
+
+    // Gaps never overlap, so they are filled concurrently as well
+    stream::iter(gaps)
+        .for_each_concurrent(state.config.sync_concurrency, |gap| async move {
+            let resource = gap.resource;
+            let pool = gap.pool.as_deref();
+            let label = resource_label(resource, pool);
+            run.add_resource(&label);
+            println!(
+                "Backfilling {} missing intervals for {} from {}",
+                gap.missing_intervals, label, gap.start_time
+            );
+
+            let max_count = state.config.midgard_max_count;
+            let windows = match gap.interval.parse() {
+                Ok(interval) => split_gap(max_count, gap.start_time, gap.end_time, interval),
+                Err(error) => {
+                    eprintln!("Skipping {label} gap | {error}");
+                    return;
+                }
+            };
+            let result = sync_windows(
+                state,
+                run,
+                resource,
+                pool,
+                windows,
+                WindowProgress::Untracked,
+            );
+            if let Err(error) = result.await {
+                eprintln!("Failed to backfill {label} gap | {error:#}");
+                record_error(state, run, resource, pool, &error).await;
             }
-        };
+        })
+        .await;
+}
 
-        // Fetch and insert earnings history
-        let earnings_history = match handlers::fetch_earnings_history(params.clone()).await {
-            Ok(data) => data,
-            Err(error) => {
-                eprintln!("Failed to fetch earnings history | {error}");
-                return;
+// Fills a gap at the resolution around it. A coarse gap that ends mid-interval,
+// where hourly history resumes, has its tail fetched hourly so the rows never overlap
+fn split_gap(
+    max_count: u16,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    interval: Interval,
+) -> Vec<Window> {
+    let boundary = interval.truncate(end_time).max(start_time);
+    let mut windows = split_windows(max_count, start_time, boundary, interval);
+    windows.extend(split_windows(max_count, boundary, end_time, Interval::Hour));
+    windows
+}
+
+// Splits [start_time, end_time) into windows of at most max_count intervals
+fn split_windows(
+    max_count: u16,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    interval: Interval,
+) -> Vec<Window> {
+    let span = interval.duration() * i32::from(max_count);
+    let mut windows = Vec::new();
+    let mut window_start = start_time;
+    while window_start < end_time {
+        let window_end = (window_start + span).min(end_time);
+        windows.push(Window {
+            start_time: window_start,
+            end_time: window_end,
+            interval,
+        });
+        window_start = window_end;
+    }
+    windows
+}
+
+// Fetches independent windows in parallel under the shared rate limiter.
+// Returns false if shutdown or lost leadership stopped it early, or the first error once every
+// window has been attempted
+async fn sync_windows(
+    state: &AppState,
+    run: &SyncRunContext,
+    resource: Resource,
+    pool: Option<&str>,
+    windows: Vec<Window>,
+    progress: WindowProgress,
+) -> Result<bool> {
+    let max_count = state.config.midgard_max_count;
+    let results: Vec<Result<bool>> = stream::iter(windows)
+        .map(|window| async move {
+            if run.should_stop(state) {
+                run.mark_interrupted();
+                return Ok(false);
             }
-        };
-        match insertions::insert_earnings_history(db_pool, &earnings_history).await {
-            Ok(data) => data,
-            Err(error) => {
-                eprintln!("Failed to insert earnings history: {error}");
-                return;
+            let count = window_count(&window, max_count);
+            let params = midgard_params(window.interval, window.start_time, count);
+            let window = sync_window(state, run, resource, pool, params, progress);
+            match run.while_leader(window).await {
+                Some(result) => result.map(|_| true),
+                None => Ok(false),
             }
-        };
+        })
+        .buffer_unordered(state.config.backfill_concurrency)
+        .collect()
+        .await;
 
-        // Fetch and insert rune pool history
-        let rune_pool_history = match handlers::fetch_rune_pool_history(params.clone()).await {
-            Ok(data) => data,
+    let mut completed = true;
+    let mut first_error = None;
+    for result in results {
+        match result {
+            Ok(done) => completed &= done,
             Err(error) => {
-                eprintln!("Failed to fetch rune pool history | {error}");
-                return;
+                eprintln!(
+                    "Failed to sync {} window | {error:#}",
+                    resource_label(resource, pool)
+                );
+                first_error.get_or_insert(error);
             }
-        };
-        match insertions::insert_rune_pool_history(db_pool, &rune_pool_history).await {
-            Ok(data) => data,
-            Err(error) => {
-                eprintln!("Failed to insert rune pool history: {error}");
-                return;
+        }
+    }
+    match first_error {
+        Some(error) => Err(error),
+        None => Ok(completed),
+    }
+}
+
+// Intervals to request for a window, a partial last interval counts as a whole one
+fn window_count(window: &Window, max_count: u16) -> u16 {
+    let step = window.interval.duration().num_seconds();
+    let span = (window.end_time - window.start_time).num_seconds();
+    let intervals = (span + step - 1) / step;
+    intervals.clamp(1, i64::from(max_count)) as u16
+}
+
+// Windows from start_time up to end_time, at the coarse interval for
+// history older than hourly_from and hourly after that
+fn plan_backfill(
+    max_count: u16,
+    coarse_interval: Option<Interval>,
+    hourly_from: DateTime<Utc>,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Vec<Window> {
+    let start_time = Interval::Hour.truncate(start_time).min(end_time);
+
+    let mut windows = Vec::new();
+    let mut hourly_start_time = start_time;
+    if let Some(coarse) = coarse_interval {
+        // Rounded down, so coarse intervals never overlap the hourly rows after end_time
+        let boundary = coarse.truncate(hourly_from.min(end_time));
+        let coarse_start_time = coarse.truncate(start_time);
+        if coarse_start_time < boundary {
+            windows.extend(split_windows(
+                max_count,
+                coarse_start_time,
+                boundary,
+                coarse,
+            ));
+            hourly_start_time = boundary;
+        }
+    }
+    windows.extend(split_windows(
+        max_count,
+        hourly_start_time,
+        end_time,
+        Interval::Hour,
+    ));
+    windows
+}
+
+// The range a backfill still has to cover: up to now on the first sync, or the
+// history older than what was already requested once BACKFILL_FROM moves back.
+// Deployments synced before backfills were tracked fall back to the first stored interval
+async fn backfill_range_for(
+    state: &AppState,
+    resource: Resource,
+    pool: Option<&str>,
+    cursor: Option<DateTime<Utc>>,
+) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let config = &state.config;
+    let now = get_truncated_now();
+    let start_time = config.backfill_from[&resource].resolve(now, config.midgard_genesis);
+
+    let end_time = match cursor {
+        Some(cursor) => {
+            let covered_from = match get_backfilled_from(&state.db_pool, resource, pool).await? {
+                Some(backfilled_from) => Some(backfilled_from),
+                None => get_first_start_time(&state.db_pool, resource, pool).await?,
+            };
+            covered_from.unwrap_or(cursor).min(cursor)
+        }
+        None => now,
+    };
+    Ok((start_time, end_time))
+}
+
+// Fetches history missing before the cursor: the range up to now on a first
+// sync, or older history once the horizon is moved back. The range is planned
+// as windows once, fetched in parallel, and resumed from the unfinished
+// windows after a restart. Returns the cursor to continue from, or None to stop
+async fn historical_backfill(
+    state: &AppState,
+    run: &SyncRunContext,
+    resource: Resource,
+    pool: Option<&str>,
+    cursor: Option<DateTime<Utc>>,
+) -> Result<Option<DateTime<Utc>>> {
+    let db_pool = &*state.db_pool;
+    let label = resource_label(resource, pool);
+
+    let mut windows = get_backfill_windows(db_pool, resource, pool).await?;
+    if windows.is_empty() {
+        let (start_time, end_time) = backfill_range_for(state, resource, pool, cursor).await?;
+        let config = &state.config;
+        // A horizon before genesis leaves the whole range hourly
+        let hourly_from = get_truncated_now()
+            .checked_sub_signed(Duration::days(i64::from(config.backfill_hourly_days)))
+            .unwrap_or(config.midgard_genesis);
+        let planned: Vec<_> = plan_backfill(
+            config.midgard_max_count,
+            config.backfill_coarse_interval,
+            hourly_from,
+            start_time,
+            end_time,
+        )
+        .into_iter()
+        .map(|window| (window.start_time, window.end_time, window.interval))
+        .collect();
+
+        if planned.is_empty() {
+            if let Some(cursor) = cursor {
+                return Ok(Some(cursor));
             }
-        };
+            // A horizon at or after now leaves nothing to backfill, so
+            // ingestion starts from the current hour instead
+            println!("BACKFILL_FROM for {label} is not in the past, syncing from {end_time}");
+            let mut tx = db_pool.begin().await?;
+            set_cursor(&mut *tx, resource, pool, end_time).await?;
+            set_backfilled_from(&mut *tx, resource, pool, end_time).await?;
+            tx.commit().await?;
+            return Ok(Some(end_time));
+        }
+        plan_backfill_windows(db_pool, resource, pool, &planned).await?;
+        windows = get_backfill_windows(db_pool, resource, pool).await?;
+    }
+    let (Some(start_time), Some(end_time)) = (
+        windows.iter().map(|window| window.start_time).min(),
+        windows.iter().map(|window| window.end_time).max(),
+    ) else {
+        return Ok(cursor);
+    };
 
-        // Fetch and insert swaps history
-        let swaps_history = match handlers::fetch_swaps_history(params.clone()).await {
-            Ok(data) => data,
-            Err(error) => {
-                eprintln!("Failed to fetch swaps history | {error}");
+    let pending = windows
+        .iter()
+        .filter(|window| !window.completed)
+        .map(|window| {
+            Ok(Window {
+                start_time: window.start_time,
+                end_time: window.end_time,
+                interval: window.interval.parse().map_err(anyhow::Error::msg)?,
+            })
+        })
+        .collect::<Result<Vec<Window>>>()?;
+    println!(
+        "Backfilling {label}: {} of {} windows left, up to {end_time}",
+        pending.len(),
+        windows.len()
+    );
+
+    let progress = WindowProgress::BackfillWindow;
+    if !sync_windows(state, run, resource, pool, pending, progress).await? {
+        return Ok(None);
+    }
+
+    // Every window is in, hand over to the cursor. It stops before intervals
+    // that are still provisional, and older history backfilled behind an
+    // existing cursor leaves it where it was
+    let final_before = Utc::now() - state.midgard_client.finality_grace;
+    let end_time = end_time.min(Interval::Hour.truncate(final_before));
+    let cursor = cursor.map_or(end_time, |cursor| cursor.max(end_time));
+    let mut tx = db_pool.begin().await?;
+    set_cursor(&mut *tx, resource, pool, cursor).await?;
+    set_backfilled_from(&mut *tx, resource, pool, start_time).await?;
+    clear_backfill_windows(&mut *tx, resource, pool).await?;
+    tx.commit().await?;
+
+    Ok(Some(cursor))
+}
+
+async fn sync_resource(
+    state: &AppState,
+    run: &SyncRunContext,
+    resource: Resource,
+    pool: Option<&str>,
+) {
+    let db_pool = &*state.db_pool;
+    let label = resource_label(resource, pool);
+    run.add_resource(&label);
+
+    // Calculate where this resource left off
+    let cursor = match get_cursor(db_pool, resource, pool).await {
+        Ok(cursor) => cursor,
+        Err(error) => {
+            eprintln!("Failed to get cursor for {label} | {error}");
+            run.add_error(format!("Failed to get cursor for {label}: {error}"));
+            return;
+        }
+    };
+    let mut last_end_time = match historical_backfill(state, run, resource, pool, cursor).await {
+        Ok(Some(end_time)) => end_time,
+        Ok(None) => return,
+        Err(error) => {
+            eprintln!("Failed to backfill {label} | {error:#}");
+            record_error(state, run, resource, pool, &error).await;
+            return;
+        }
+    };
+    let mut current_iteration: u32 = 1;
+
+    // Perform fetch and insert operations until only the open interval is left
+    while last_end_time < Utc::now() {
+        // Windows already written stay committed, the rest resumes from the cursor
+        if run.should_stop(state) {
+            println!("Stopping {label} sync for shutdown or lost leadership");
+            run.mark_interrupted();
+            return;
+        }
+
+        let params = midgard_params(
+            Interval::Hour,
+            last_end_time,
+            state.config.midgard_max_count,
+        );
+
+        println!(
+            "\n\n------------{} | Iteration: {} | From: {}------------\n",
+            label, current_iteration, &params.from
+        );
+
+        let progress = WindowProgress::Cursor;
+        let window = sync_window(state, run, resource, pool, params, progress);
+        let window_end_time = match run.while_leader(window).await {
+            None => {
+                println!("Stopping {label} sync, this replica lost ingestion leadership");
                 return;
             }
-        };
-        match insertions::insert_swaps_history(db_pool, &swaps_history).await {
-            Ok(data) => data,
-            Err(error) => {
-                eprintln!("Failed to insert swaps history: {error}");
+            Some(Ok(Some(end_time))) => end_time,
+            Some(Ok(None)) => {
+                println!("No new closed intervals for {label}");
+                return;
             }
-        };
-
-        // Update last_end_time
-        last_end_time = match get_last_end_time(db_pool, "depth_price_history").await {
-            Ok(time) => time.unwrap_or(default_start_time),
-            Err(error) => {
-                eprintln!("Failed to get last end time | {error}");
+            Some(Err(error)) => {
+                eprintln!("Failed to sync {label} | {error:#}");
+                record_error(state, run, resource, pool, &error).await;
                 return;
             }
         };
+
+        last_end_time = window_end_time;
         current_iteration += 1;
     }
 }
+
+fn resource_label(resource: Resource, pool: Option<&str>) -> String {
+    match pool {
+        Some(pool) => format!("{resource} ({pool})"),
+        None => resource.to_string(),
+    }
+}
+
+async fn record_error(
+    state: &AppState,
+    run: &SyncRunContext,
+    resource: Resource,
+    pool: Option<&str>,
+    error: &anyhow::Error,
+) {
+    let label = resource_label(resource, pool);
+    run.add_error(format!("{label}: {error:#}"));
+
+    let result = record_upstream_error(
+        &state.db_pool,
+        run.id,
+        run.started_at,
+        resource,
+        pool,
+        error,
+    )
+    .await;
+    if let Err(db_error) = result {
+        eprintln!("Failed to record upstream error for {resource} | {db_error}");
+    }
+}
+
+// Fetches one window and writes it with its progress in a single transaction,
+// returning the end time of its last closed interval
+async fn sync_window(
+    state: &AppState,
+    run: &SyncRunContext,
+    resource: Resource,
+    pool: Option<&str>,
+    params: midgard_api::Params,
+    progress: WindowProgress,
+) -> Result<Option<DateTime<Utc>>> {
+    with_history_record!(resource, Record => {
+        sync_window_of::<Record>(state, run, resource, pool, params, progress).await
+    })
+}
+
+async fn sync_window_of<T: HistoryRecord>(
+    state: &AppState,
+    run: &SyncRunContext,
+    resource: Resource,
+    pool: Option<&str>,
+    params: midgard_api::Params,
+    progress: WindowProgress,
+) -> Result<Option<DateTime<Utc>>> {
+    let db_pool = &*state.db_pool;
+    let midgard_client = &state.midgard_client;
+    let window_start_time = params.from;
+
+    let fetch_pool = resource.is_per_pool().then(|| pool.unwrap_or(DEFAULT_POOL));
+    let page =
+        midgard_api::fetch_history::<T>(midgard_client, fetch_pool, params, &run.retries).await?;
+
+    // Only open the transaction once the upstream fetch has succeeded
+    let mut tx = db_pool.begin().await?;
+    let final_before = page.raw.fetched_at - midgard_client.finality_grace;
+    let stored = store_intervals(
+        &mut tx,
+        page.intervals,
+        fetch_pool,
+        &page.params.interval,
+        final_before,
+        |_| true,
+    )
+    .await?;
+    let (rows, last_end_time) = (stored.rows, stored.last_final_end_time);
+    let (meta, params, raw) = (page.meta, page.params, page.raw);
+    archive_response(&mut *tx, resource, pool, &params, &raw).await?;
+    record_sync_run_window(
+        &mut *tx,
+        run.id,
+        resource,
+        pool,
+        &params,
+        rows,
+        raw.fetched_at,
+    )
+    .await?;
+    if let Some(meta) = &meta {
+        upsert_history_meta(
+            &mut *tx,
+            resource,
+            pool,
+            &params.interval,
+            meta,
+            raw.fetched_at,
+        )
+        .await?;
+    }
+
+    match (progress, last_end_time) {
+        // Provisional intervals are re-fetched, so the cursor stops before them
+        (WindowProgress::Cursor, Some(last_end_time)) => {
+            set_cursor(&mut *tx, resource, pool, last_end_time).await?
+        }
+        (WindowProgress::BackfillWindow, _) => {
+            complete_backfill_window(&mut *tx, resource, pool, window_start_time).await?
+        }
+        _ => {}
+    }
+    tx.commit().await?;
+    run.add_window(rows);
+
+    Ok(last_end_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, day, hour, minute, 0).unwrap()
+    }
+
+    fn window(start_time: DateTime<Utc>, end_time: DateTime<Utc>, interval: Interval) -> Window {
+        Window {
+            start_time,
+            end_time,
+            interval,
+        }
+    }
+
+    #[test]
+    fn splits_into_windows_of_max_count() {
+        assert_eq!(
+            split_windows(2, at(1, 0, 0), at(1, 5, 0), Interval::Hour),
+            vec![
+                window(at(1, 0, 0), at(1, 2, 0), Interval::Hour),
+                window(at(1, 2, 0), at(1, 4, 0), Interval::Hour),
+                window(at(1, 4, 0), at(1, 5, 0), Interval::Hour),
+            ]
+        );
+    }
+
+    #[test]
+    fn window_ending_mid_interval_requests_the_partial_interval() {
+        let windows = split_windows(2, at(1, 0, 0), at(1, 2, 30), Interval::Hour);
+        assert_eq!(
+            windows,
+            vec![
+                window(at(1, 0, 0), at(1, 2, 0), Interval::Hour),
+                window(at(1, 2, 0), at(1, 2, 30), Interval::Hour),
+            ]
+        );
+        assert_eq!(window_count(&windows[0], 2), 2);
+        assert_eq!(window_count(&windows[1], 2), 1);
+        assert_eq!(
+            window_count(&window(at(1, 0, 0), at(3, 12, 0), Interval::Day), 400),
+            3
+        );
+    }
+
+    #[test]
+    fn window_count_stays_within_max_count() {
+        let long = window(at(1, 0, 0), at(20, 0, 0), Interval::Hour);
+        assert_eq!(window_count(&long, 400), 400);
+        let empty = window(at(1, 0, 0), at(1, 0, 0), Interval::Hour);
+        assert_eq!(window_count(&empty, 400), 1);
+    }
+
+    #[test]
+    fn start_after_end_plans_nothing() {
+        assert!(split_windows(400, at(2, 0, 0), at(1, 0, 0), Interval::Hour).is_empty());
+        assert!(plan_backfill(400, None, at(1, 0, 0), at(2, 0, 0), at(1, 0, 0)).is_empty());
+        assert!(plan_backfill(
+            400,
+            Some(Interval::Day),
+            at(5, 0, 0),
+            at(3, 5, 0),
+            at(1, 0, 0)
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn backfill_is_hourly_without_a_coarse_interval() {
+        assert_eq!(
+            plan_backfill(400, None, at(3, 0, 0), at(1, 22, 30), at(2, 1, 0)),
+            vec![window(at(1, 22, 0), at(2, 1, 0), Interval::Hour)]
+        );
+    }
+
+    #[test]
+    fn coarse_history_hands_over_at_the_midnight_before_hourly_from() {
+        // hourly_from at 13:00 is rounded down, the whole of day 4 is hourly
+        let windows = plan_backfill(
+            400,
+            Some(Interval::Day),
+            at(4, 13, 0),
+            at(1, 5, 0),
+            at(5, 2, 0),
+        );
+        assert_eq!(
+            windows,
+            vec![
+                window(at(1, 0, 0), at(4, 0, 0), Interval::Day),
+                window(at(4, 0, 0), at(5, 2, 0), Interval::Hour),
+            ]
+        );
+    }
+
+    #[test]
+    fn backfill_ending_before_hourly_from_stays_coarse_up_to_its_midnight() {
+        let windows = plan_backfill(
+            400,
+            Some(Interval::Day),
+            at(10, 0, 0),
+            at(1, 0, 0),
+            at(3, 6, 0),
+        );
+        assert_eq!(
+            windows,
+            vec![
+                window(at(1, 0, 0), at(3, 0, 0), Interval::Day),
+                window(at(3, 0, 0), at(3, 6, 0), Interval::Hour),
+            ]
+        );
+    }
+
+    #[test]
+    fn backfill_within_one_day_is_hourly() {
+        let windows = plan_backfill(
+            400,
+            Some(Interval::Day),
+            at(10, 0, 0),
+            at(3, 2, 0),
+            at(3, 6, 0),
+        );
+        assert_eq!(
+            windows,
+            vec![window(at(3, 2, 0), at(3, 6, 0), Interval::Hour)]
+        );
+    }
+
+    #[test]
+    fn gap_ending_mid_day_has_an_hourly_tail() {
+        assert_eq!(
+            split_gap(400, at(1, 0, 0), at(3, 13, 0), Interval::Day),
+            vec![
+                window(at(1, 0, 0), at(3, 0, 0), Interval::Day),
+                window(at(3, 0, 0), at(3, 13, 0), Interval::Hour),
+            ]
+        );
+    }
+
+    #[test]
+    fn gap_inside_one_day_is_hourly() {
+        assert_eq!(
+            split_gap(400, at(3, 4, 0), at(3, 13, 0), Interval::Day),
+            vec![window(at(3, 4, 0), at(3, 13, 0), Interval::Hour)]
+        );
+    }
+
+    #[test]
+    fn hourly_gap_is_split_by_max_count() {
+        assert_eq!(
+            split_gap(3, at(1, 0, 0), at(1, 5, 0), Interval::Hour),
+            vec![
+                window(at(1, 0, 0), at(1, 3, 0), Interval::Hour),
+                window(at(1, 3, 0), at(1, 5, 0), Interval::Hour),
+            ]
+        );
+    }
+}
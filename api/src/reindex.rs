@@ -0,0 +1,149 @@
+use crate::db::history_meta::upsert_history_meta;
+use crate::db::raw_responses::{get_archived_responses, ArchivedResponse};
+use crate::history::{store_intervals, with_history_record, HistoryRecord};
+use crate::midgard_api::{self, HistoryResponse};
+use crate::models::Resource;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool};
+
+const REINDEX_BATCH_SIZE: i64 = 100;
+
+// Which archived responses a reindex replays, and which of their intervals it keeps
+pub struct ReindexScope {
+    pub resource: Option<Resource>,
+    pub pool: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl ReindexScope {
+    fn contains(&self, start_time: DateTime<Utc>) -> bool {
+        self.from.is_none_or(|from| start_time >= from) && self.to.is_none_or(|to| start_time < to)
+    }
+}
+
+// Rebuilds the history tables from the raw response archive without calling
+// Midgard. Responses are replayed in fetch order, so later fetches win as they did live
+pub async fn reindex(
+    db_pool: &PgPool,
+    scope: &ReindexScope,
+    finality_grace: Duration,
+) -> Result<()> {
+    let mut after = None;
+    let mut replayed = 0;
+    let mut skipped = 0;
+    let mut rows = 0;
+
+    loop {
+        let batch = get_archived_responses(
+            db_pool,
+            scope.resource,
+            scope.pool.as_deref(),
+            scope.from,
+            scope.to,
+            after,
+            REINDEX_BATCH_SIZE,
+        )
+        .await?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        after = Some((last.fetched_at, last.id));
+
+        let mut tx = db_pool.begin().await?;
+        for response in &batch {
+            match replay_response(&mut tx, response, scope, finality_grace).await? {
+                Some(count) => {
+                    replayed += 1;
+                    rows += count;
+                }
+                None => skipped += 1,
+            }
+        }
+        tx.commit().await?;
+    }
+
+    println!(
+        "Reindexed {} archived responses ({} rows upserted, {} skipped)",
+        replayed, rows, skipped
+    );
+    Ok(())
+}
+
+// Upserts the in-scope intervals of one response, or None if it no longer decodes
+async fn replay_response(
+    conn: &mut PgConnection,
+    response: &ArchivedResponse,
+    scope: &ReindexScope,
+    finality_grace: Duration,
+) -> Result<Option<usize>> {
+    let resource: Resource = match response.resource.parse() {
+        Ok(resource) => resource,
+        Err(error) => {
+            eprintln!("Skipping archived response {} | {error}", response.id);
+            return Ok(None);
+        }
+    };
+
+    let replayed = with_history_record!(resource, Record => {
+        replay_intervals::<Record>(conn, response, scope, finality_grace).await?
+    });
+    let Some((rows, meta)) = replayed else {
+        return Ok(None);
+    };
+    if let Some(meta) = &meta {
+        upsert_history_meta(
+            &mut *conn,
+            resource,
+            Some(&response.pool),
+            &response.interval,
+            meta,
+            response.fetched_at,
+        )
+        .await?;
+    }
+
+    Ok(Some(rows))
+}
+
+// Decodes one response as `T` and upserts its in-scope intervals, returning
+// how many were stored and Midgard's range summary
+async fn replay_intervals<T: HistoryRecord>(
+    conn: &mut PgConnection,
+    response: &ArchivedResponse,
+    scope: &ReindexScope,
+    finality_grace: Duration,
+) -> Result<Option<(usize, Option<Value>)>> {
+    let Some(HistoryResponse { intervals, meta }) = decode::<T>(response) else {
+        return Ok(None);
+    };
+    let stored = store_intervals(
+        conn,
+        intervals,
+        Some(&response.pool),
+        &response.interval,
+        response.fetched_at - finality_grace,
+        |start_time| scope.contains(start_time),
+    )
+    .await?;
+    Ok(Some((stored.rows, meta)))
+}
+
+// Parses an archived body with the current models
+fn decode<T: DeserializeOwned>(response: &ArchivedResponse) -> Option<HistoryResponse<T>> {
+    let decoded = response
+        .decompressed_body()
+        .map_err(anyhow::Error::from)
+        .and_then(|body| Ok(midgard_api::parse_history(&body)?));
+
+    match decoded {
+        Ok(resp) => Some(resp),
+        Err(error) => {
+            eprintln!("Skipping archived response {} | {error}", response.id);
+            None
+        }
+    }
+}
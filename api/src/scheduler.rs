@@ -1,12 +1,247 @@
-use crate::populate_db::populate_db;
-use sqlx::PgPool;
-use std::sync::Arc;
+use crate::models::{Resource, SyncTrigger};
+use crate::populate_db::{populate_db, populate_pools};
+use crate::state::AppState;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use std::{fmt, str::FromStr};
 use tokio::time::{self, Duration};
 
-pub async fn start_scheduler(db_pool: Arc<PgPool>) {
-    let mut interval = time::interval(Duration::from_secs(3600));
-    loop {
-        interval.tick().await;
-        populate_db(&db_pool).await;
+#[derive(Clone, Debug)]
+pub enum Schedule {
+    Every(Duration),
+    Cron(Box<cron::Schedule>),
+}
+
+impl Schedule {
+    // Intervals start right away, cron schedules wait for their first match
+    fn first_run(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Every(_) => Some(now),
+            Schedule::Cron(schedule) => schedule.after(&now).next(),
+        }
+    }
+
+    // Runs missed while the previous one was still going are skipped
+    fn next_run(&self, previous: DateTime<Utc>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Every(period) => {
+                let period = chrono::Duration::from_std(*period).ok()?;
+                let mut next = previous.checked_add_signed(period)?;
+                while next <= now {
+                    next = next.checked_add_signed(period)?;
+                }
+                Some(next)
+            }
+            Schedule::Cron(schedule) => schedule.after(&now).next(),
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Every(period) => write!(f, "every {}s", period.as_secs()),
+            Schedule::Cron(schedule) => write!(f, "cron `{schedule}`"),
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    // Either an interval such as `300`, `90s`, `5m`, `1h` or `1d`, or a cron expression
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.contains(' ') {
+            // Five-field expressions get a leading seconds field
+            let expression = match value.split_whitespace().count() {
+                5 => format!("0 {value}"),
+                _ => value.to_string(),
+            };
+            return cron::Schedule::from_str(&expression)
+                .map(|schedule| Schedule::Cron(Box::new(schedule)))
+                .map_err(|e| format!("Invalid cron expression {value}: {e}"));
+        }
+
+        let (number, unit) = value.split_at(
+            value
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(value.len()),
+        );
+        let multiplier = match unit {
+            "" | "s" => 1,
+            "m" => 60,
+            "h" => 3600,
+            "d" => 86_400,
+            _ => return Err(format!("Invalid schedule: {value}")),
+        };
+        match number
+            .parse::<u64>()
+            .ok()
+            .and_then(|count| count.checked_mul(multiplier))
+        {
+            Some(secs) if secs > 0 => Ok(Schedule::Every(Duration::from_secs(secs))),
+            _ => Err(format!("Invalid schedule: {value}")),
+        }
+    }
+}
+
+pub async fn start_scheduler(state: AppState) {
+    let handles: Vec<_> = Resource::ALL
+        .into_iter()
+        .map(|resource| state.tasks.spawn(run_schedule(state.clone(), resource)))
+        .collect();
+
+    for handle in handles {
+        if let Err(error) = handle.await {
+            eprintln!("Scheduler task failed | {error}");
+        }
+    }
+}
+
+async fn run_schedule(state: AppState, resource: Resource) {
+    let schedule = &state.config.sync_schedules[&resource];
+    println!("Scheduling {resource} sync {schedule}");
+
+    let mut next_run = schedule.first_run(Utc::now());
+    while let Some(run_at) = next_run {
+        // Jitter spreads out resources (and replicas) that share a schedule
+        let jitter_secs = state.config.sync_jitter_secs;
+        let jitter = Duration::from_secs(rand::thread_rng().gen_range(0..=jitter_secs));
+        let delay = (run_at - Utc::now()).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = time::sleep(delay + jitter) => {}
+            _ = state.shutdown.cancelled() => return,
+        }
+
+        if !state.leadership.is_leader() {
+            println!("Skipping scheduled {resource} sync, this replica is not the leader");
+        } else if let Some(_guard) = state.sync_locks.try_lock(resource) {
+            // The pool catalog decides which pools depth is ingested for
+            if resource.is_per_pool() {
+                populate_pools(&state).await;
+            }
+            populate_db(&state, &[resource], SyncTrigger::Scheduler).await;
+        } else {
+            // A manual sync or backfill still holds the resource
+            println!("Skipping scheduled {resource} sync, a previous run is still going");
+        }
+
+        next_run = schedule.next_run(run_at, Utc::now());
+    }
+
+    eprintln!("Schedule for {resource} has no upcoming runs");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, hour, minute, second)
+            .unwrap()
+    }
+
+    fn period(value: &str) -> Duration {
+        match value.parse::<Schedule>() {
+            Ok(Schedule::Every(period)) => period,
+            other => panic!("{value} should parse as an interval, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_intervals() {
+        assert_eq!(period("300"), Duration::from_secs(300));
+        assert_eq!(period("90s"), Duration::from_secs(90));
+        assert_eq!(period("5m"), Duration::from_secs(300));
+        assert_eq!(period("1h"), Duration::from_secs(3600));
+        assert_eq!(period("1d"), Duration::from_secs(86_400));
+        assert_eq!(period(" 2h "), Duration::from_secs(7200));
+    }
+
+    #[test]
+    fn rejects_invalid_intervals() {
+        for value in [
+            "0",
+            "0m",
+            "5x",
+            "h",
+            "",
+            "-5m",
+            "1.5h",
+            "300000000000000d",
+            "18446744073709551616",
+        ] {
+            assert!(
+                value.parse::<Schedule>().is_err(),
+                "{value} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn five_field_cron_runs_at_second_zero() {
+        let schedule: Schedule = "15 * * * *".parse().unwrap();
+        assert!(matches!(schedule, Schedule::Cron(_)));
+        assert_eq!(schedule.first_run(at(10, 20, 0)), Some(at(11, 15, 0)));
+        assert_eq!(
+            schedule.next_run(at(11, 15, 0), at(11, 15, 30)),
+            Some(at(12, 15, 0))
+        );
+    }
+
+    #[test]
+    fn six_field_cron_keeps_its_seconds() {
+        let schedule: Schedule = "30 15 * * * *".parse().unwrap();
+        assert_eq!(schedule.first_run(at(10, 20, 0)), Some(at(11, 15, 30)));
+    }
+
+    #[test]
+    fn rejects_invalid_cron() {
+        assert!("61 * * * *".parse::<Schedule>().is_err());
+        assert!("* * *".parse::<Schedule>().is_err());
+    }
+
+    #[test]
+    fn intervals_start_right_away() {
+        let schedule: Schedule = "1h".parse().unwrap();
+        assert_eq!(schedule.first_run(at(10, 20, 0)), Some(at(10, 20, 0)));
+    }
+
+    #[test]
+    fn next_run_follows_the_period() {
+        let schedule: Schedule = "1h".parse().unwrap();
+        assert_eq!(
+            schedule.next_run(at(10, 0, 0), at(10, 5, 0)),
+            Some(at(11, 0, 0))
+        );
+    }
+
+    #[test]
+    fn next_run_skips_runs_missed_by_a_long_run() {
+        let schedule: Schedule = "1h".parse().unwrap();
+        // The 11:00 and 12:00 runs were missed while the 10:00 one was going
+        assert_eq!(
+            schedule.next_run(at(10, 0, 0), at(12, 30, 0)),
+            Some(at(13, 0, 0))
+        );
+        // A run due exactly now is skipped too, it would start late anyway
+        assert_eq!(
+            schedule.next_run(at(10, 0, 0), at(11, 0, 0)),
+            Some(at(12, 0, 0))
+        );
+
+        let schedule: Schedule = "15 * * * *".parse().unwrap();
+        assert_eq!(
+            schedule.next_run(at(10, 15, 0), at(13, 40, 0)),
+            Some(at(14, 15, 0))
+        );
+    }
+
+    #[test]
+    fn period_past_the_last_date_has_no_next_run() {
+        let schedule: Schedule = "100000000000d".parse().unwrap();
+        assert_eq!(schedule.next_run(at(10, 0, 0), at(10, 5, 0)), None);
     }
 }
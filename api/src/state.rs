@@ -0,0 +1,57 @@
+use crate::{config::Config, leader::Leadership, midgard_api::MidgardClient, models::Resource};
+use axum::extract::FromRef;
+use sqlx::PgPool;
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+#[derive(Clone)]
+pub struct AppState {
+    pub db_pool: Arc<PgPool>,
+    pub config: Arc<Config>,
+    pub midgard_client: Arc<MidgardClient>,
+    pub sync_locks: Arc<SyncLocks>,
+    pub leadership: Arc<Leadership>,
+    // Cancelled on SIGTERM/SIGINT; ingestion stops after its current window
+    pub shutdown: CancellationToken,
+    // Ingestion tasks that shutdown waits for before closing the pool
+    pub tasks: TaskTracker,
+}
+
+impl FromRef<AppState> for Arc<PgPool> {
+    fn from_ref(state: &AppState) -> Self {
+        Arc::clone(&state.db_pool)
+    }
+}
+
+// One lock per resource, held while it is synced or backfilled so runs never overlap
+pub struct SyncLocks {
+    locks: HashMap<Resource, Arc<Mutex<()>>>,
+}
+
+impl SyncLocks {
+    pub fn new() -> Self {
+        Self {
+            locks: Resource::ALL
+                .into_iter()
+                .map(|resource| (resource, Arc::new(Mutex::new(()))))
+                .collect(),
+        }
+    }
+
+    pub async fn lock(&self, resource: Resource) -> OwnedMutexGuard<()> {
+        Arc::clone(&self.locks[&resource]).lock_owned().await
+    }
+
+    // None while another run holds the resource
+    pub fn try_lock(&self, resource: Resource) -> Option<OwnedMutexGuard<()>> {
+        Arc::clone(&self.locks[&resource]).try_lock_owned().ok()
+    }
+
+    pub fn try_lock_all(&self) -> Option<Vec<OwnedMutexGuard<()>>> {
+        Resource::ALL
+            .into_iter()
+            .map(|resource| self.try_lock(resource))
+            .collect()
+    }
+}
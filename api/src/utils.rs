@@ -14,10 +14,12 @@ pub fn init_tracing() {
 }
 
 pub fn get_truncated_now() -> chrono::DateTime<Utc> {
-    let now = Utc::now();
+    truncate_to_hour(Utc::now())
+}
 
-    now.date_naive()
-        .and_hms_opt(now.hour(), 0, 0)
+pub fn truncate_to_hour(time: chrono::DateTime<Utc>) -> chrono::DateTime<Utc> {
+    time.date_naive()
+        .and_hms_opt(time.hour(), 0, 0)
         .expect("Failed to truncate to hour")
         .and_utc()
 }